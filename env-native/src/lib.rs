@@ -0,0 +1,288 @@
+use chrono::{DateTime, Utc};
+use futures::{future, Future, Stream};
+use lazy_static::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use stremio_core::state_types::*;
+use tokio::executor::current_thread::spawn;
+use tokio::timer::Delay;
+
+const DEFAULT_STORAGE_PATH: &str = "stremio-storage.json";
+
+#[derive(Debug)]
+pub enum EnvError {
+    Reqwest(reqwest::Error),
+    Serde(serde_json::error::Error),
+    Io(io::Error),
+    Timer(tokio::timer::Error),
+    HTTPStatusCode(u16),
+}
+impl fmt::Display for EnvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl Error for EnvError {
+    fn description(&self) -> &str {
+        match self {
+            EnvError::Reqwest(e) => e.description(),
+            EnvError::Serde(e) => e.description(),
+            EnvError::Io(e) => e.description(),
+            EnvError::Timer(e) => e.description(),
+            EnvError::HTTPStatusCode(_) => "unexpected HTTP status code",
+        }
+    }
+}
+impl From<reqwest::Error> for EnvError {
+    fn from(e: reqwest::Error) -> EnvError {
+        EnvError::Reqwest(e)
+    }
+}
+impl From<serde_json::error::Error> for EnvError {
+    fn from(e: serde_json::error::Error) -> EnvError {
+        EnvError::Serde(e)
+    }
+}
+impl From<io::Error> for EnvError {
+    fn from(e: io::Error) -> EnvError {
+        EnvError::Io(e)
+    }
+}
+impl From<tokio::timer::Error> for EnvError {
+    fn from(e: tokio::timer::Error) -> EnvError {
+        EnvError::Timer(e)
+    }
+}
+
+lazy_static! {
+    static ref FILE_STORAGE: RwLock<FileStorage> = RwLock::new(FileStorage {
+        path: PathBuf::from(DEFAULT_STORAGE_PATH),
+        entries: None,
+    });
+    static ref MEMORY_STORAGE: RwLock<BTreeMap<String, String>> = Default::default();
+}
+
+// All the keys are kept in one JSON object, which is read once and rewritten on every change
+struct FileStorage {
+    path: PathBuf,
+    entries: Option<BTreeMap<String, serde_json::Value>>,
+}
+impl FileStorage {
+    fn entries(&mut self) -> Result<&mut BTreeMap<String, serde_json::Value>, EnvError> {
+        if self.entries.is_none() {
+            let entries = match fs::read(&self.path) {
+                Ok(contents) => serde_json::from_slice(&contents)?,
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+                Err(e) => return Err(e.into()),
+            };
+            self.entries = Some(entries);
+        }
+        Ok(self.entries.get_or_insert_with(BTreeMap::new))
+    }
+    fn get<T: DeserializeOwned>(&mut self, key: &str) -> Result<Option<T>, EnvError> {
+        Ok(match self.entries()?.get(key) {
+            Some(v) => Some(serde_json::from_value(v.to_owned())?),
+            None => None,
+        })
+    }
+    fn set<T: Serialize>(&mut self, key: &str, value: Option<&T>) -> Result<(), EnvError> {
+        let entries = self.entries()?;
+        match value {
+            Some(v) => entries.insert(key.to_owned(), serde_json::to_value(v)?),
+            None => entries.remove(key),
+        };
+        let serialized = serde_json::to_vec(entries)?;
+        // Write to a temporary file first, so that a crash never leaves us with a truncated storage
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, serialized)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+fn wrap_to_fut<T: 'static>(res: Result<T, EnvError>) -> EnvFuture<T> {
+    Box::new(match res {
+        Ok(res) => future::ok(res),
+        Err(e) => future::err(e.into()),
+    })
+}
+
+fn delay(duration: Duration) -> EnvFuture<()> {
+    Box::new(Delay::new(Instant::now() + duration).map_err(|e| EnvError::from(e).into()))
+}
+
+fn fetch<IN, OUT>(in_req: Request<IN>) -> EnvFuture<OUT>
+where
+    IN: 'static + Serialize,
+    OUT: 'static + DeserializeOwned,
+{
+    let (parts, body) = in_req.into_parts();
+    let method = reqwest::Method::from_bytes(parts.method.as_str().as_bytes())
+        .expect("method is not valid for reqwest");
+    let mut req = reqwest::r#async::Client::new().request(method, &parts.uri.to_string());
+    for (k, v) in parts.headers.iter() {
+        req = req.header(k.as_str(), v.as_ref());
+    }
+    // GET requests are built with a () body, which would otherwise be sent as `null`
+    if serde_json::to_value(&body).is_ok_and(|v| !v.is_null()) {
+        req = req.json(&body);
+    }
+    let fut = req
+        .send()
+        .map_err(EnvError::from)
+        .and_then(|res: reqwest::r#async::Response| {
+            if res.status().is_success() {
+                future::Either::A(res.into_body().concat2().map_err(EnvError::from))
+            } else {
+                future::Either::B(future::err(EnvError::HTTPStatusCode(res.status().as_u16())))
+            }
+        })
+        .and_then(|body| serde_json::from_slice(&body).map_err(EnvError::from))
+        .map_err(Into::into);
+    Box::new(fut)
+}
+
+// Native environment, which persists the storage in a JSON file
+// Effects are spawned on the current thread, so it must be used within a
+// tokio::runtime::current_thread runtime
+// By creating an empty enum, we ensure that this type cannot be initialized
+pub enum Env {}
+impl Env {
+    // Must be called before the storage is used for the first time
+    pub fn set_storage_path(path: impl Into<PathBuf>) {
+        let mut storage = FILE_STORAGE.write().expect("storage lock poisoned");
+        storage.path = path.into();
+        storage.entries = None;
+    }
+}
+impl Environment for Env {
+    fn fetch_serde<IN, OUT>(in_req: Request<IN>) -> EnvFuture<OUT>
+    where
+        IN: 'static + Serialize,
+        OUT: 'static + DeserializeOwned,
+    {
+        fetch(in_req)
+    }
+    fn exec(fut: Box<dyn Future<Item = (), Error = ()>>) {
+        spawn(fut);
+    }
+    fn get_storage<T: 'static + DeserializeOwned>(key: &str) -> EnvFuture<Option<T>> {
+        let mut storage = FILE_STORAGE.write().expect("storage lock poisoned");
+        wrap_to_fut(storage.get(key))
+    }
+    fn set_storage<T: Serialize>(key: &str, value: Option<&T>) -> EnvFuture<()> {
+        let mut storage = FILE_STORAGE.write().expect("storage lock poisoned");
+        wrap_to_fut(storage.set(key, value))
+    }
+    fn now() -> DateTime<Utc> {
+        Utc::now()
+    }
+    fn delay(duration: Duration) -> EnvFuture<()> {
+        delay(duration)
+    }
+}
+
+// Same as Env, but the storage is only kept in memory; useful for tests and one-off tools
+pub enum EnvMemory {}
+impl EnvMemory {
+    pub fn clear_storage() {
+        MEMORY_STORAGE
+            .write()
+            .expect("storage lock poisoned")
+            .clear();
+    }
+}
+impl Environment for EnvMemory {
+    fn fetch_serde<IN, OUT>(in_req: Request<IN>) -> EnvFuture<OUT>
+    where
+        IN: 'static + Serialize,
+        OUT: 'static + DeserializeOwned,
+    {
+        fetch(in_req)
+    }
+    fn exec(fut: Box<dyn Future<Item = (), Error = ()>>) {
+        spawn(fut);
+    }
+    fn get_storage<T: 'static + DeserializeOwned>(key: &str) -> EnvFuture<Option<T>> {
+        let storage = MEMORY_STORAGE.read().expect("storage lock poisoned");
+        wrap_to_fut(match storage.get(key) {
+            Some(v) => serde_json::from_str(v).map(Some).map_err(EnvError::from),
+            None => Ok(None),
+        })
+    }
+    fn set_storage<T: Serialize>(key: &str, value: Option<&T>) -> EnvFuture<()> {
+        let mut storage = MEMORY_STORAGE.write().expect("storage lock poisoned");
+        wrap_to_fut(match value {
+            Some(v) => serde_json::to_string(v)
+                .map(|v| {
+                    storage.insert(key.to_owned(), v);
+                })
+                .map_err(EnvError::from),
+            None => {
+                storage.remove(key);
+                Ok(())
+            }
+        })
+    }
+    fn now() -> DateTime<Utc> {
+        Utc::now()
+    }
+    fn delay(duration: Duration) -> EnvFuture<()> {
+        delay(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_storage() {
+        let path = std::env::temp_dir().join("env-native-test-storage.json");
+        let _ = fs::remove_file(&path);
+        Env::set_storage_path(&path);
+
+        let key = "foo";
+        let value = "foobar".to_owned();
+        assert_eq!(Env::get_storage::<String>(key).wait().unwrap(), None);
+        Env::set_storage(key, Some(&value)).wait().unwrap();
+        assert_eq!(
+            Env::get_storage(key).wait().unwrap(),
+            Some(value.to_owned())
+        );
+
+        // Re-read from the file, rather than the in-memory copy
+        Env::set_storage_path(&path);
+        assert_eq!(Env::get_storage(key).wait().unwrap(), Some(value));
+        Env::set_storage::<String>(key, None).wait().unwrap();
+        assert_eq!(Env::get_storage::<String>(key).wait().unwrap(), None);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn memory_storage() {
+        EnvMemory::clear_storage();
+
+        let key = "foo";
+        let value = "foobar".to_owned();
+        assert_eq!(EnvMemory::get_storage::<String>(key).wait().unwrap(), None);
+        EnvMemory::set_storage(key, Some(&value)).wait().unwrap();
+        assert_eq!(EnvMemory::get_storage(key).wait().unwrap(), Some(value));
+        EnvMemory::set_storage::<String>(key, None).wait().unwrap();
+        assert_eq!(EnvMemory::get_storage::<String>(key).wait().unwrap(), None);
+
+        EnvMemory::set_storage(key, Some(&"bar".to_owned()))
+            .wait()
+            .unwrap();
+        EnvMemory::clear_storage();
+        assert_eq!(EnvMemory::get_storage::<String>(key).wait().unwrap(), None);
+    }
+}
@@ -1,7 +1,9 @@
+use chrono::{DateTime, TimeZone, Utc};
 use futures::{future, Future};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::collections::HashMap;
+use std::time::Duration;
 use stremio_core::state_types::*;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
@@ -17,6 +19,7 @@ pub enum EnvError {
     Serde(serde_json::error::Error),
     HTTPStatusCode(u16),
     StorageMissing,
+    WindowMissing,
 }
 impl fmt::Display for EnvError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -30,6 +33,7 @@ impl Error for EnvError {
             EnvError::Serde(e) => &e.description(),
             EnvError::HTTPStatusCode(_) => "unexpected HTTP status code",
             EnvError::StorageMissing => "localStorage is missing",
+            EnvError::WindowMissing => "window is missing",
         }
     }
 }
@@ -142,4 +146,27 @@ impl Environment for Env {
     fn set_storage<T: Serialize>(key: &str, value: Option<&T>) -> EnvFuture<()> {
         Self::wrap_to_fut(Self::set_storage_sync(key, value))
     }
+    fn now() -> DateTime<Utc> {
+        Utc.timestamp_millis(js_sys::Date::now() as i64)
+    }
+    fn delay(duration: Duration) -> EnvFuture<()> {
+        // setTimeout takes an i32, so longer delays are clamped
+        let millis = duration.as_millis().min(i32::MAX as u128) as i32;
+        let window = match web_sys::window() {
+            Some(window) => window,
+            None => return Box::new(future::err(EnvError::WindowMissing.into())),
+        };
+        let pr = js_sys::Promise::new(&mut |resolve, reject| {
+            if let Err(e) =
+                window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis)
+            {
+                let _ = reject.call1(&JsValue::UNDEFINED, &e);
+            }
+        });
+        Box::new(
+            JsFuture::from(pr)
+                .map(|_| ())
+                .map_err(|e| EnvError::from(e).into()),
+        )
+    }
 }
@@ -68,7 +68,9 @@ impl From<Vec<MetaPreview>> for ResourceResponse {
 }
 impl From<MetaDetail> for ResourceResponse {
     fn from(meta: MetaDetail) -> Self {
-        ResourceResponse::Meta { meta }
+        ResourceResponse::Meta {
+            meta: Box::new(meta),
+        }
     }
 }
 impl From<Vec<Stream>> for ResourceResponse {
@@ -1,6 +1,8 @@
 use crate::state_types::{EnvFuture, Environment, Request};
 use crate::types::addons::*;
 use futures::future::err;
+use futures::Future;
+use serde_derive::*;
 use std::marker::PhantomData;
 
 mod legacy;
@@ -12,6 +14,18 @@ pub const LEGACY_PATH: &str = "/stremio/v1";
 pub trait AddonInterface {
     fn get(&self, path: &ResourceRef) -> EnvFuture<ResourceResponse>;
     fn manifest(&self) -> EnvFuture<Manifest>;
+    // Transports which have no notion of caching hints don't need to implement this
+    fn get_with_hints(&self, path: &ResourceRef) -> EnvFuture<(ResourceResponse, CacheHints)> {
+        Box::new(self.get(path).map(|resp| (resp, CacheHints::default())))
+    }
+}
+
+#[derive(Deserialize)]
+struct ResourceResponseWithHints {
+    #[serde(flatten)]
+    response: ResourceResponse,
+    #[serde(flatten)]
+    hints: CacheHints,
 }
 
 #[derive(Default)]
@@ -29,8 +43,11 @@ impl<T: Environment> AddonHTTPTransport<T> {
 }
 impl<T: Environment> AddonInterface for AddonHTTPTransport<T> {
     fn get(&self, path: &ResourceRef) -> EnvFuture<ResourceResponse> {
+        Box::new(self.get_with_hints(path).map(|(resp, _)| resp))
+    }
+    fn get_with_hints(&self, path: &ResourceRef) -> EnvFuture<(ResourceResponse, CacheHints)> {
         if self.transport_url.ends_with(LEGACY_PATH) {
-            return AddonLegacyTransport::<T>::from_url(&self.transport_url).get(&path);
+            return AddonLegacyTransport::<T>::from_url(&self.transport_url).get_with_hints(&path);
         }
 
         if !self.transport_url.ends_with(MANIFEST_PATH) {
@@ -41,7 +58,10 @@ impl<T: Environment> AddonInterface for AddonHTTPTransport<T> {
 
         let url = self.transport_url.replace(MANIFEST_PATH, &path.to_string());
         let r = Request::get(&url).body(()).expect("builder cannot fail");
-        T::fetch_serde::<_, ResourceResponse>(r)
+        Box::new(
+            T::fetch_serde::<_, ResourceResponseWithHints>(r)
+                .map(|ResourceResponseWithHints { response, hints }| (response, hints)),
+        )
     }
     fn manifest(&self) -> EnvFuture<Manifest> {
         if self.transport_url.ends_with(LEGACY_PATH) {
@@ -1,8 +1,44 @@
-use super::msg::Msg;
+use super::msg::{Internal, Msg};
+use derivative::*;
+use futures::future::{Either, Shared};
+use futures::sync::oneshot;
 use futures::{future, Future};
+use std::sync::{Arc, Mutex};
 
 pub type Effect = Box<dyn Future<Item = Msg, Error = Msg>>;
 
+// Effects bound to a handle (see Effects::cancellable) are cancelled by EffectsHandle::cancel,
+// e.g. when a model resets it on a new load; cancelled effects resolve to EffectCancelled
+// Clones of a handle (e.g. from cloning the model) share it, so any of them can cancel
+#[derive(Derivative, Clone)]
+#[derivative(Debug)]
+pub struct EffectsHandle {
+    #[derivative(Debug = "ignore")]
+    tx: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+    #[derivative(Debug = "ignore")]
+    rx: Shared<oneshot::Receiver<()>>,
+}
+impl Default for EffectsHandle {
+    fn default() -> Self {
+        let (tx, rx) = oneshot::channel();
+        EffectsHandle {
+            tx: Arc::new(Mutex::new(Some(tx))),
+            rx: rx.shared(),
+        }
+    }
+}
+impl EffectsHandle {
+    pub fn cancel(&self) {
+        // Dropping the sender is what cancels the effects
+        self.tx.lock().expect("effects handle lock poisoned").take();
+    }
+    // Cancels the effects of this handle, and replaces it with a new one
+    pub fn reset(&mut self) {
+        self.cancel();
+        *self = EffectsHandle::default();
+    }
+}
+
 pub struct Effects {
     pub effects: Vec<Effect>,
     pub has_changed: bool,
@@ -39,6 +75,22 @@ impl Effects {
         self
     }
 
+    pub fn cancellable(mut self, handle: &EffectsHandle) -> Self {
+        self.effects = self
+            .effects
+            .into_iter()
+            .map(|ft| -> Effect {
+                // The receiver never gets a value, it only fails once the sender is dropped
+                Box::new(ft.select2(handle.rx.clone()).then(|res| match res {
+                    Ok(Either::A((msg, _))) => future::ok(msg),
+                    Err(Either::A((msg, _))) => future::err(msg),
+                    _ => future::err(Msg::Internal(Internal::EffectCancelled)),
+                }))
+            })
+            .collect();
+        self
+    }
+
     pub fn join(mut self, mut x: Effects) -> Self {
         self.has_changed = self.has_changed || x.has_changed;
         self.effects.append(&mut x.effects);
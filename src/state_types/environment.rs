@@ -1,8 +1,11 @@
 use crate::addon_transport::{AddonHTTPTransport, AddonInterface};
-use futures::Future;
+use chrono::{DateTime, Utc};
+use futures::{future, Future};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::error::Error;
+use std::fmt;
+use std::time::Duration;
 
 pub use http::Request;
 
@@ -10,6 +13,17 @@ const API_URL: &str = "https://api.strem.io";
 
 pub type EnvError = Box<dyn Error>;
 pub type EnvFuture<T> = Box<dyn Future<Item = T, Error = EnvError>>;
+
+// The error of futures which did not resolve in time; see Environment::timeout
+#[derive(Debug)]
+pub struct TimeoutError;
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "timed out")
+    }
+}
+impl Error for TimeoutError {}
+
 pub trait Environment {
     // https://serde.rs/lifetimes.html#trait-bounds
     fn fetch_serde<IN, OUT>(request: Request<IN>) -> EnvFuture<OUT>
@@ -19,6 +33,17 @@ pub trait Environment {
     fn exec(fut: Box<dyn Future<Item = (), Error = ()>>);
     fn get_storage<T: 'static + DeserializeOwned>(key: &str) -> EnvFuture<Option<T>>;
     fn set_storage<T: Serialize>(key: &str, value: Option<&T>) -> EnvFuture<()>;
+    // Not every target has a system clock that chrono can read (e.g. wasm32-unknown-unknown)
+    fn now() -> DateTime<Utc>;
+    fn delay(duration: Duration) -> EnvFuture<()>;
+    fn timeout<T: 'static>(ft: EnvFuture<T>, duration: Duration) -> EnvFuture<T>
+    where
+        Self: Sized,
+    {
+        let timeout =
+            Self::delay(duration).then(|_| future::err::<T, EnvError>(TimeoutError.into()));
+        Box::new(ft.select(timeout).map(|(x, _)| x).map_err(|(e, _)| e))
+    }
     fn addon_transport(url: &str) -> Box<dyn AddonInterface>
     where
         Self: Sized + 'static,
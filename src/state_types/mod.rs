@@ -10,6 +10,9 @@ pub use self::effects::*;
 mod models;
 pub use self::models::*;
 
+mod storage;
+pub use self::storage::*;
+
 mod runtime;
 pub use self::runtime::*;
 
@@ -0,0 +1,122 @@
+use crate::state_types::msg::Internal::*;
+use crate::state_types::*;
+use crate::types::addons::{ResourceRequest, ResourceResponse};
+use chrono::{DateTime, Duration, Utc};
+use futures::{future, Future};
+use serde_derive::*;
+
+const STORAGE_SLOT: &str = "addon_cache";
+// The whole cache is persisted, so it's kept rather small
+const MAX_ENTRIES: usize = 100;
+// Responses tend to come in bursts (e.g. a board), so they are persisted together
+const PERSIST_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddonCacheEntry {
+    pub req: ResourceRequest,
+    pub response: ResourceResponse,
+    pub fetched: DateTime<Utc>,
+    // In seconds; responses without a cacheMaxAge hint are stale right away,
+    // which means that they are rendered, but always revalidated
+    pub max_age: u64,
+}
+impl AddonCacheEntry {
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        now < self.fetched + Duration::seconds(self.max_age as i64)
+    }
+}
+
+// The AddonCache is kept in the Ctx; models may use it through addon_aggr_new_cached/addon_get_cached
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AddonCache {
+    // Ordered from the oldest to the most recently fetched
+    entries: Vec<AddonCacheEntry>,
+    #[serde(skip)]
+    is_persist_scheduled: bool,
+}
+impl AddonCache {
+    pub fn get(&self, req: &ResourceRequest) -> Option<&AddonCacheEntry> {
+        self.entries.iter().find(|entry| &entry.req == req)
+    }
+    pub fn insert(&mut self, entry: AddonCacheEntry) {
+        self.entries.retain(|x| x.req != entry.req);
+        self.entries.push(entry);
+        if self.entries.len() > MAX_ENTRIES {
+            let overflow = self.entries.len() - MAX_ENTRIES;
+            self.entries.drain(..overflow);
+        }
+    }
+    pub fn load_from_storage<Env: Environment + 'static>(&self) -> Effects {
+        let ft = Env::get_storage::<AddonCache>(STORAGE_SLOT)
+            .map(|cache| AddonCacheLoaded(cache.unwrap_or_default()).into())
+            // The cache is not essential, so we can just start over
+            .or_else(|_| future::ok(AddonCacheLoaded(AddonCache::default()).into()));
+        Effects::one(Box::new(ft)).unchanged()
+    }
+    pub fn update<Env: Environment + 'static>(&mut self, msg: &Msg) -> Effects {
+        match msg {
+            Msg::Internal(AddonCacheLoaded(cache)) => {
+                // Anything we've fetched in the meantime is newer than what was stored
+                let mut entries = cache.entries.to_owned();
+                entries.retain(|entry| self.get(&entry.req).is_none());
+                entries.append(&mut self.entries);
+                entries.sort_by_key(|entry| entry.fetched);
+                self.entries = entries;
+                if self.entries.len() > MAX_ENTRIES {
+                    let overflow = self.entries.len() - MAX_ENTRIES;
+                    self.entries.drain(..overflow);
+                }
+                Effects::none().unchanged()
+            }
+            Msg::Internal(AddonCacheableResponse(req, resp)) => {
+                let (response, hints) = resp.as_ref();
+                self.insert(AddonCacheEntry {
+                    req: req.to_owned(),
+                    response: response.to_owned(),
+                    fetched: Env::now(),
+                    max_age: hints.cache_max_age.unwrap_or(0),
+                });
+                Effects::msg(
+                    AddonResponse(req.to_owned(), Box::new(Ok(response.to_owned()))).into(),
+                )
+                .join(self.schedule_persist::<Env>())
+                .unchanged()
+            }
+            // Otherwise, it would never be persisted again
+            Msg::Internal(AddonCachePersistCancelled) => {
+                self.is_persist_scheduled = false;
+                Effects::none().unchanged()
+            }
+            Msg::Internal(AddonCachePersist) => {
+                self.is_persist_scheduled = false;
+                let ft = Env::set_storage(STORAGE_SLOT, Some(self))
+                    // Failing to persist the cache is not a problem, it's just not there next time
+                    .then(|_| future::ok(AddonCachePersisted.into()));
+                Effects::one(Box::new(ft)).unchanged()
+            }
+            // The responses of an add-on which was removed or upgraded are outdated
+            Msg::Action(Action::AddonOp(ActionAddon::Remove { transport_url }))
+            | Msg::Event(Event::AddonUpgraded(transport_url, ..)) => {
+                let entries_len = self.entries.len();
+                self.entries
+                    .retain(|entry| entry.req.base != *transport_url);
+                if self.entries.len() < entries_len {
+                    self.schedule_persist::<Env>()
+                } else {
+                    Effects::none().unchanged()
+                }
+            }
+            _ => Effects::none().unchanged(),
+        }
+    }
+    fn schedule_persist<Env: Environment + 'static>(&mut self) -> Effects {
+        if self.is_persist_scheduled {
+            return Effects::none().unchanged();
+        }
+        self.is_persist_scheduled = true;
+        let ft = Env::delay(PERSIST_DELAY)
+            .map(|_| AddonCachePersist.into())
+            .map_err(|_| AddonCachePersistCancelled.into());
+        Effects::one(Box::new(ft)).unchanged()
+    }
+}
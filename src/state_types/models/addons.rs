@@ -1,7 +1,11 @@
 use crate::state_types::msg::Internal::*;
 use crate::state_types::*;
-use crate::types::addons::{AggrRequest, Descriptor, ResourceRequest, ResourceResponse};
+use crate::types::addons::{AggrRequest, Descriptor, Manifest, ResourceRequest, ResourceResponse};
 use futures::{future, Future};
+use std::time::Duration;
+
+// Add-ons that take longer than this are considered unavailable
+const ADDON_TIMEOUT: Duration = Duration::from_secs(20);
 
 pub trait Group {
     fn new(addon: &Descriptor, req: ResourceRequest) -> Self;
@@ -21,6 +25,26 @@ pub fn addon_aggr_new<Env: Environment + 'static, G: Group>(
     (groups, Effects::many(effects))
 }
 
+// Same as addon_aggr_new, but the groups are populated with the cached responses right away
+pub fn addon_aggr_new_cached<Env: Environment + 'static, G: Group>(
+    addons: &[Descriptor],
+    cache: &AddonCache,
+    aggr_req: &AggrRequest,
+) -> (Vec<G>, Effects) {
+    let (effects, groups): (Vec<_>, Vec<_>) = aggr_req
+        .plan(addons)
+        .into_iter()
+        .map(|(addon, addon_req)| {
+            let mut group = G::new(addon, addon_req);
+            (addon_get_cached::<Env, _>(cache, &mut group), group)
+        })
+        .unzip();
+    (
+        groups,
+        Effects::many(effects.into_iter().flatten().collect()),
+    )
+}
+
 pub fn addon_aggr_update<G: Group>(groups: &mut Vec<G>, msg: &Msg) -> Effects {
     match msg {
         Msg::Internal(AddonResponse(req, result)) => {
@@ -39,11 +63,55 @@ pub fn addon_get<Env: Environment + 'static>(req: &ResourceRequest) -> Effect {
     // we will need that, cause we have to move it into the closure
     let req = req.clone();
     Box::new(
-        Env::addon_transport(&req.base)
-            .get(&req.path)
-            .then(move |res| match res {
-                Ok(_) => future::ok(AddonResponse(req, Box::new(res)).into()),
-                Err(_) => future::err(AddonResponse(req, Box::new(res)).into()),
-            }),
+        Env::timeout(
+            Env::addon_transport(&req.base).get(&req.path),
+            ADDON_TIMEOUT,
+        )
+        .then(move |res| match res {
+            Ok(_) => future::ok(AddonResponse(req, Box::new(res)).into()),
+            Err(_) => future::err(AddonResponse(req, Box::new(res)).into()),
+        }),
+    )
+}
+
+pub fn addon_manifest<Env: Environment + 'static>(transport_url: &str) -> EnvFuture<Manifest> {
+    Env::timeout(
+        Env::addon_transport(transport_url).manifest(),
+        ADDON_TIMEOUT,
+    )
+}
+
+// Updates the group with the cached response (if any); the add-on is only requested
+// if there's no cached response or if it's stale, in which case it's revalidated
+pub fn addon_get_cached<Env: Environment + 'static, G: Group>(
+    cache: &AddonCache,
+    group: &mut G,
+) -> Option<Effect> {
+    let req = group.addon_req().to_owned();
+    match cache.get(&req) {
+        Some(entry) => {
+            group.update(&Ok(entry.response.to_owned()));
+            if entry.is_fresh(Env::now()) {
+                None
+            } else {
+                Some(addon_get_cacheable::<Env>(&req))
+            }
+        }
+        None => Some(addon_get_cacheable::<Env>(&req)),
+    }
+}
+
+fn addon_get_cacheable<Env: Environment + 'static>(req: &ResourceRequest) -> Effect {
+    let req = req.clone();
+    Box::new(
+        Env::timeout(
+            Env::addon_transport(&req.base).get_with_hints(&req.path),
+            ADDON_TIMEOUT,
+        )
+        .then(move |res| match res {
+            Ok(resp) => future::ok(AddonCacheableResponse(req, Box::new(resp)).into()),
+            // Errors are not cached, so they go straight to the models
+            Err(e) => future::err(AddonResponse(req, Box::new(Err(e))).into()),
+        }),
     )
 }
@@ -0,0 +1,87 @@
+use crate::state_types::*;
+use crate::types::addons::{Descriptor, DescriptorPreview};
+use serde_derive::*;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AddonsDiscoveryEntry {
+    pub addon: DescriptorPreview,
+    // Installed from the same URL, or an add-on with the same id is installed
+    pub is_installed: bool,
+    // The installed add-on with the same id has an older version; both installing
+    // and upgrading is done with ActionAddon::InstallFromUrl, which fetches the full manifest
+    pub is_upgradable: bool,
+}
+
+// The add-on catalogs (e.g. an add-on store), along with what's already installed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AddonsDiscovery {
+    pub catalog: CatalogFiltered<DescriptorPreview>,
+    // Only the add-ons which support this type are in .entries
+    pub type_filter: Option<String>,
+    pub entries: Vec<AddonsDiscoveryEntry>,
+}
+impl<Env: Environment + 'static> UpdateWithCtx<Ctx<Env>> for AddonsDiscovery {
+    fn update(&mut self, ctx: &Ctx<Env>, msg: &Msg) -> Effects {
+        let fx = match msg {
+            Msg::Action(Action::Load(ActionLoad::AddonsDiscovery {
+                request,
+                type_filter,
+            })) => {
+                self.type_filter = type_filter.to_owned();
+                let load = Msg::Action(Action::Load(ActionLoad::CatalogFiltered(
+                    request.to_owned(),
+                )));
+                self.catalog.update(ctx, &load)
+            }
+            // That's meant for the catalogs of metas, which may be loaded at the same time
+            Msg::Action(Action::Load(ActionLoad::CatalogFiltered(_))) => {
+                Effects::none().unchanged()
+            }
+            _ => self.catalog.update(ctx, msg),
+        };
+        // The installed add-ons may have changed as well
+        let entries = discovery_entries(
+            &self.catalog.content,
+            &ctx.content.addons,
+            &self.type_filter,
+        );
+        if entries != self.entries {
+            self.entries = entries;
+            fx.join(Effects::none())
+        } else {
+            fx
+        }
+    }
+}
+
+fn discovery_entries(
+    content: &Loadable<Vec<DescriptorPreview>, CatalogError>,
+    installed: &[Descriptor],
+    type_filter: &Option<String>,
+) -> Vec<AddonsDiscoveryEntry> {
+    let previews = match content {
+        Loadable::Ready(previews) => previews,
+        _ => return vec![],
+    };
+    previews
+        .iter()
+        .filter(|preview| match type_filter {
+            Some(type_name) => preview.manifest.types.contains(type_name),
+            None => true,
+        })
+        .map(|preview| {
+            let same_id = installed
+                .iter()
+                .find(|addon| addon.manifest.id == preview.manifest.id);
+            let same_url = installed
+                .iter()
+                .any(|addon| addon.transport_url == preview.transport_url);
+            AddonsDiscoveryEntry {
+                addon: preview.to_owned(),
+                is_installed: same_url || same_id.is_some(),
+                is_upgradable: same_id
+                    .is_some_and(|addon| addon.manifest.version < preview.manifest.version),
+            }
+        })
+        .collect()
+}
@@ -11,17 +11,22 @@ use std::convert::TryFrom;
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct CatalogGrouped {
     pub groups: Vec<ItemsGroup<Vec<MetaPreview>>>,
+    // Reset on every load, which cancels the effects of the previous one
+    #[serde(skip)]
+    load_effects: EffectsHandle,
 }
 impl<Env: Environment + 'static> UpdateWithCtx<Ctx<Env>> for CatalogGrouped {
     fn update(&mut self, ctx: &Ctx<Env>, msg: &Msg) -> Effects {
         match msg {
             Msg::Action(Action::Load(ActionLoad::CatalogGrouped { extra })) => {
-                let (groups, effects) = addon_aggr_new::<Env, _>(
+                let (groups, effects) = addon_aggr_new_cached::<Env, _>(
                     &ctx.content.addons,
+                    &ctx.addon_cache,
                     &AggrRequest::AllCatalogs { extra },
                 );
                 self.groups = groups;
-                effects
+                self.load_effects.reset();
+                effects.cancellable(&self.load_effects)
             }
             _ => addon_aggr_update(&mut self.groups, msg),
         }
@@ -94,6 +99,9 @@ pub struct CatalogFiltered<T> {
     pub load_prev: Option<ResourceRequest>,
     // NOTE: There's no currently selected preview item, cause some UIs may not have this
     // so, it should be implemented in the UI
+    // Reset on every load, which cancels the effects of the previous one
+    #[serde(skip)]
+    load_effects: EffectsHandle,
 }
 
 impl<Env, T> UpdateWithCtx<Ctx<Env>> for CatalogFiltered<T>
@@ -110,6 +118,7 @@ where
                 // only show catalogs for the selected type, or all of them
                 let catalogs: Vec<CatalogEntry> = addons
                     .iter()
+                    .filter(|a| !a.flags.disabled)
                     .flat_map(|a| {
                         T::catalogs(&a.manifest).iter().filter_map(move |cat| {
                             // Required properties are allowed, but only if there's .options
@@ -157,7 +166,7 @@ where
                     })
                     .collect();
                 // Find the selected catalog, and get it's extra_iter
-                let selectable_extra = get_catalog(addons, &selected_req)
+                let selectable_extra = get_catalog::<T>(addons, &selected_req)
                     .map(|cat| {
                         cat.extra_iter()
                             .filter(|x| x.options.iter().flatten().next().is_some())
@@ -167,6 +176,7 @@ where
                     .unwrap_or_default();
                 // Reset the model state
                 // content will be Loadable::Loading
+                self.load_effects.cancel();
                 *self = CatalogFiltered {
                     catalogs,
                     types,
@@ -174,12 +184,12 @@ where
                     selected: Some(selected_req.to_owned()),
                     ..Default::default()
                 };
-                Effects::one(addon_get::<Env>(&selected_req))
+                Effects::one(addon_get::<Env>(&selected_req)).cancellable(&self.load_effects)
             }
             Msg::Internal(AddonResponse(req, resp))
                 if Some(req) == self.selected.as_ref() && self.content == Loadable::Loading =>
             {
-                let skippable = get_catalog(addons, &req)
+                let skippable = get_catalog::<T>(addons, &req)
                     .map(|cat| cat.extra_iter().any(|e| e.name == SKIP))
                     .unwrap_or(false);
                 let len = match resp.as_ref() {
@@ -208,7 +218,7 @@ where
                         Ok(x) => Loadable::Ready(x.into_iter().take(PAGE_LEN as usize).collect()),
                         Err(_) => Loadable::Err(CatalogError::UnexpectedResp),
                     },
-                    Err(e) => Loadable::Err(CatalogError::Other(e.to_string())),
+                    Err(e) => Loadable::Err(e.into()),
                 };
                 Effects::none()
             }
@@ -217,12 +227,15 @@ where
     }
 }
 
-fn get_catalog<'a>(addons: &'a [Descriptor], req: &ResourceRequest) -> Option<&'a ManifestCatalog> {
+fn get_catalog<'a, T: CatalogAdapter>(
+    addons: &'a [Descriptor],
+    req: &ResourceRequest,
+) -> Option<&'a ManifestCatalog> {
     addons
         .iter()
         .find(|a| a.transport_url == req.base)
         .iter()
-        .flat_map(|a| &a.manifest.catalogs)
+        .flat_map(|a| T::catalogs(&a.manifest))
         .find(|cat| cat.type_name == req.path.type_name && cat.id == req.path.id)
 }
 
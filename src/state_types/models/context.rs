@@ -1,15 +1,19 @@
 use crate::state_types::Event::*;
 use crate::state_types::Internal::*;
 use crate::state_types::*;
-use crate::types::addons::Descriptor;
+use crate::types::addons::{board_catalogs, Descriptor, Manifest, ManifestIssue, TransportUrl};
 use crate::types::api::*;
 use derivative::*;
-use futures::Future;
+use futures::{future, Future};
 use lazy_static::*;
+use semver::Version;
 use serde_derive::*;
 use std::marker::PhantomData;
+use std::time::Duration;
+
+// The manifests of the installed add-ons are refreshed in the background, see RefreshManifests
+const REFRESH_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
 
-const USER_DATA_KEY: &str = "userData";
 lazy_static! {
     static ref DEFAULT_ADDONS: Vec<Descriptor> = serde_json::from_slice(include_bytes!(
         "../../../stremio-official-addons/index.json"
@@ -28,6 +32,7 @@ pub struct Auth {
 pub struct CtxContent {
     pub auth: Option<Auth>,
     pub addons: Vec<Descriptor>,
+    #[serde(default, deserialize_with = "deserialize_stored_settings")]
     pub settings: Settings,
 }
 impl Default for CtxContent {
@@ -39,6 +44,26 @@ impl Default for CtxContent {
         }
     }
 }
+impl CtxContent {
+    // Salvages every field which can be deserialized from stored content which can't be
+    // deserialized as a whole; the rest falls back to the defaults
+    fn recover(stored: &serde_json::Value) -> Option<Self> {
+        let stored = stored.as_object()?;
+        let field = |name| stored.get(name).cloned().unwrap_or_default();
+        let addons = match field("addons") {
+            serde_json::Value::Array(addons) => addons
+                .into_iter()
+                .filter_map(|addon| serde_json::from_value(addon).ok())
+                .collect(),
+            _ => DEFAULT_ADDONS.to_owned(),
+        };
+        Some(CtxContent {
+            auth: serde_json::from_value(field("auth")).unwrap_or_default(),
+            addons,
+            settings: deserialize_stored_settings(field("settings")).unwrap_or_default(),
+        })
+    }
+}
 
 #[derive(Derivative, Serialize)]
 #[derivative(Debug, Default, Clone)]
@@ -48,23 +73,164 @@ pub struct Ctx<Env: Environment> {
     pub is_loaded: bool,
     #[serde(skip)]
     pub library: LibraryLoadable,
+    #[serde(skip)]
+    pub addon_cache: AddonCache,
+    #[serde(skip)]
+    pub lib_sync: LibSync,
+    // Reset whenever the ctx is loaded, so that there's only one refresh scheduled
+    #[serde(skip)]
+    refresh_timer: EffectsHandle,
     #[derivative(Debug = "ignore")]
     #[serde(skip)]
     env: PhantomData<Env>,
 }
 
+impl<Env: Environment + 'static> Ctx<Env> {
+    fn schedule_refresh(&self) -> Effects {
+        let ft = Env::delay(REFRESH_INTERVAL)
+            .map(|_| AddonsRefreshScheduled.into())
+            .map_err(|_| EffectCancelled.into());
+        Effects::one(Box::new(ft))
+            .cancellable(&self.refresh_timer)
+            .unchanged()
+    }
+    // Changes which are synced with the add-on collection
+    fn save_and_push(&self) -> Effects {
+        let fx = Effects::one(save_storage::<Env>(&self.content));
+        if self.content.auth.is_some() {
+            fx.join(Effects::msg(Action::UserOp(ActionUser::PushAddons).into()))
+        } else {
+            fx
+        }
+    }
+    // The catalogs on the board, in its order
+    fn board(&self) -> Vec<(TransportUrl, String, String)> {
+        board_catalogs(&self.content.addons)
+            .into_iter()
+            .map(|(addon, cat)| {
+                (
+                    addon.transport_url.to_owned(),
+                    cat.type_name.to_owned(),
+                    cat.id.to_owned(),
+                )
+            })
+            .collect()
+    }
+    // Every catalog on the board gets its position; the catalogs which are not on it (e.g. of
+    // disabled add-ons) keep their order, after those
+    fn set_board(&mut self, board: &[(TransportUrl, String, String)]) {
+        let mut off_board = self
+            .content
+            .addons
+            .iter()
+            .flat_map(|addon| {
+                addon.flags.catalogs.iter().filter_map(move |prefs| {
+                    let catalog = (
+                        addon.transport_url.to_owned(),
+                        prefs.type_name.to_owned(),
+                        prefs.id.to_owned(),
+                    );
+                    match prefs.position {
+                        Some(position) if !board.contains(&catalog) => Some((position, catalog)),
+                        _ => None,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        off_board.sort();
+        let off_board = off_board.into_iter().map(|(_, catalog)| catalog);
+        for (position, (transport_url, type_name, id)) in
+            board.iter().cloned().chain(off_board).enumerate()
+        {
+            let addon = self
+                .content
+                .addons
+                .iter_mut()
+                .find(|addon| addon.transport_url == *transport_url)
+                .expect("catalog of an installed add-on");
+            addon.flags.catalog_prefs_mut(&type_name, &id).position = Some(position);
+        }
+    }
+    fn set_catalog_hidden(
+        &mut self,
+        transport_url: &str,
+        type_name: &str,
+        id: &str,
+        hidden: bool,
+    ) -> Effects {
+        let addon = self.content.addons.iter_mut().find(|addon| {
+            addon.transport_url == transport_url
+                && addon
+                    .manifest
+                    .catalogs
+                    .iter()
+                    .any(|cat| cat.type_name == type_name && cat.id == id)
+        });
+        match addon {
+            Some(addon)
+                if addon
+                    .flags
+                    .catalog_prefs(type_name, id)
+                    .is_some_and(|prefs| prefs.hidden)
+                    != hidden =>
+            {
+                addon.flags.catalog_prefs_mut(type_name, id).hidden = hidden;
+                self.save_and_push()
+            }
+            _ => Effects::none().unchanged(),
+        }
+    }
+    fn addon_position(&self, transport_url: &str) -> Option<usize> {
+        self.content
+            .addons
+            .iter()
+            .position(|addon| addon.transport_url == transport_url)
+    }
+    // Protected add-ons can't be disabled, for the same reason they can't be removed
+    fn set_addon_disabled(&mut self, transport_url: &str, disabled: bool) -> Effects {
+        let addon = self.content.addons.iter_mut().find(|addon| {
+            !addon.flags.protected
+                && addon.flags.disabled != disabled
+                && addon.transport_url == transport_url
+        });
+        match addon {
+            Some(addon) => {
+                addon.flags.disabled = disabled;
+                self.save_and_push()
+            }
+            None => Effects::none().unchanged(),
+        }
+    }
+    fn save_if_installed(&self, event: Event) -> Effects {
+        match event {
+            AddonInstallFailed(..) => Effects::msg(event.into()).unchanged(),
+            _ => Effects::msg(event.into()).join(self.save_and_push()),
+        }
+    }
+}
+
 impl<Env: Environment + 'static> Update for Ctx<Env> {
     fn update(&mut self, msg: &Msg) -> Effects {
         let fx = match msg {
             // Loading from storage: request it
-            Msg::Action(Action::LoadCtx) if !self.is_loaded => {
-                Effects::one(load_storage::<Env>()).unchanged()
-            }
+            Msg::Action(Action::LoadCtx) if !self.is_loaded => Effects::one(load_storage::<Env>())
+                .join(self.addon_cache.load_from_storage::<Env>())
+                .unchanged(),
+            // The storage is migrated by now, so the other slots can be loaded
             Msg::Internal(CtxLoaded(opt_content)) => {
                 self.content = *opt_content.to_owned().unwrap_or_default();
 
                 self.is_loaded = true;
-                self.library.load_from_storage::<Env>(&self.content)
+                self.refresh_timer.reset();
+                self.library
+                    .load_from_storage::<Env>(&self.content)
+                    .join(self.lib_sync.load_from_storage::<Env>())
+                    .join(self.schedule_refresh())
+            }
+            Msg::Internal(AddonsRefreshScheduled) => {
+                Effects::msg(Action::AddonOp(ActionAddon::RefreshManifests).into())
+                    .join(self.schedule_refresh())
+                    .unchanged()
             }
             // Addon install/remove
             Msg::Action(Action::AddonOp(ActionAddon::Remove { transport_url })) => {
@@ -73,19 +239,155 @@ impl<Env: Environment + 'static> Update for Ctx<Env> {
                 });
                 if let Some(position) = position {
                     self.content.addons.remove(position);
-                    Effects::one(save_storage::<Env>(&self.content))
+                    self.save_and_push()
                 } else {
                     Effects::none().unchanged()
                 }
             }
+            Msg::Action(Action::AddonOp(ActionAddon::MoveUp { transport_url })) => {
+                match self.addon_position(transport_url) {
+                    Some(position) if position > 0 => {
+                        self.content.addons.swap(position, position - 1);
+                        self.save_and_push()
+                    }
+                    _ => Effects::none().unchanged(),
+                }
+            }
+            Msg::Action(Action::AddonOp(ActionAddon::MoveDown { transport_url })) => {
+                match self.addon_position(transport_url) {
+                    Some(position) if position + 1 < self.content.addons.len() => {
+                        self.content.addons.swap(position, position + 1);
+                        self.save_and_push()
+                    }
+                    _ => Effects::none().unchanged(),
+                }
+            }
+            Msg::Action(Action::AddonOp(ActionAddon::Enable { transport_url })) => {
+                self.set_addon_disabled(transport_url, false)
+            }
+            Msg::Action(Action::AddonOp(ActionAddon::Disable { transport_url })) => {
+                self.set_addon_disabled(transport_url, true)
+            }
+            Msg::Action(Action::AddonOp(ActionAddon::HideCatalog {
+                transport_url,
+                type_name,
+                id,
+            })) => self.set_catalog_hidden(transport_url, type_name, id, true),
+            Msg::Action(Action::AddonOp(ActionAddon::ShowCatalog {
+                transport_url,
+                type_name,
+                id,
+            })) => self.set_catalog_hidden(transport_url, type_name, id, false),
+            Msg::Action(Action::AddonOp(ActionAddon::MoveCatalog {
+                transport_url,
+                type_name,
+                id,
+                position,
+            })) => {
+                let mut board = self.board();
+                let current = board.iter().position(|(url, cat_type, cat_id)| {
+                    url == transport_url && cat_type == type_name && cat_id == id
+                });
+                match current {
+                    Some(current) if current != *position => {
+                        let catalog = board.remove(current);
+                        board.insert((*position).min(board.len()), catalog);
+                        self.set_board(&board);
+                        self.save_and_push()
+                    }
+                    _ => Effects::none().unchanged(),
+                }
+            }
             Msg::Action(Action::AddonOp(ActionAddon::Install(descriptor))) => {
-                // @TODO should we dedupe?
-                self.content.addons.push(*descriptor.to_owned());
-                Effects::one(save_storage::<Env>(&self.content))
+                let event = install_addon(&mut self.content.addons, *descriptor.to_owned());
+                self.save_if_installed(event)
+            }
+            Msg::Action(Action::AddonOp(ActionAddon::InstallFromUrl(transport_url))) => {
+                let transport_url = transport_url.to_owned();
+                let ft = addon_manifest::<Env>(&transport_url).then(move |res| match res {
+                    Ok(manifest) => {
+                        future::ok(AddonManifestFetched(transport_url, Box::new(manifest)).into())
+                    }
+                    Err(e) => future::err(
+                        AddonInstallFailed(
+                            transport_url,
+                            AddonManifestError::ManifestFetch(e.to_string()),
+                        )
+                        .into(),
+                    ),
+                });
+                Effects::one(Box::new(ft)).unchanged()
+            }
+            Msg::Internal(AddonManifestFetched(transport_url, manifest)) => {
+                let descriptor = Descriptor {
+                    manifest: *manifest.to_owned(),
+                    transport_url: transport_url.to_owned(),
+                    flags: Default::default(),
+                };
+                let event = install_addon(&mut self.content.addons, descriptor);
+                self.save_if_installed(event)
+            }
+            Msg::Action(Action::AddonOp(ActionAddon::RefreshManifests)) => {
+                let fetches = self
+                    .content
+                    .addons
+                    .iter()
+                    .map(|addon| {
+                        let transport_url = addon.transport_url.to_owned();
+                        addon_manifest::<Env>(&transport_url)
+                            .then(move |res| future::ok::<_, Msg>((transport_url, res)))
+                    })
+                    .collect::<Vec<_>>();
+                let ft = future::join_all(fetches)
+                    .map(|refreshed| AddonManifestsRefreshed(refreshed).into());
+                Effects::one(Box::new(ft)).unchanged()
+            }
+            // All of them at once, so that the upgraded add-ons are pushed once
+            Msg::Internal(AddonManifestsRefreshed(refreshed)) => {
+                let events = refreshed
+                    .iter()
+                    .filter_map(|(transport_url, res)| {
+                        // Add-ons which were removed in the meantime have no result
+                        let addon = self
+                            .content
+                            .addons
+                            .iter_mut()
+                            .find(|addon| &addon.transport_url == transport_url)?;
+                        let upgraded = match res {
+                            Ok(manifest) => upgrade_manifest(addon, manifest),
+                            Err(e) => Err(AddonManifestError::ManifestFetch(e.to_string())),
+                        };
+                        let transport_url = transport_url.to_owned();
+                        Some(match upgraded {
+                            Ok(Some((from, to))) => AddonUpgraded(transport_url, from, to),
+                            Ok(None) => AddonUpToDate(transport_url),
+                            Err(e) => AddonRefreshFailed(transport_url, e),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let is_upgraded = events
+                    .iter()
+                    .any(|event| matches!(event, AddonUpgraded(..)));
+                let fx = Effects::many(
+                    events
+                        .into_iter()
+                        .map(|event| -> Effect { Box::new(future::ok(event.into())) })
+                        .collect(),
+                );
+                if is_upgraded {
+                    fx.join(self.save_and_push())
+                } else {
+                    fx.unchanged()
+                }
             }
             Msg::Action(Action::Settings(ActionSettings::Store(settings))) => {
-                self.content.settings = *settings.to_owned();
-                Effects::one(save_storage::<Env>(&self.content))
+                match settings.validate() {
+                    Ok(()) => {
+                        self.content.settings = *settings.to_owned();
+                        Effects::one(save_storage::<Env>(&self.content))
+                    }
+                    Err(e) => Effects::msg(SettingsInvalid(e).into()).unchanged(),
+                }
             }
             // User actions related to API primitives (authentication/addons)
             Msg::Action(Action::UserOp(action)) => match action.to_owned() {
@@ -159,7 +461,15 @@ impl<Env: Environment + 'static> Update for Ctx<Env> {
                     None => Effects::none().unchanged(),
                 },
                 // We let the LibraryLoadable model handle this
-                ActionUser::LibSync | ActionUser::LibUpdate(_) => Effects::none().unchanged(),
+                ActionUser::LibSync
+                | ActionUser::LibUpdate(_)
+                | ActionUser::AddToLibrary(_)
+                | ActionUser::RemoveFromLibrary(_)
+                | ActionUser::RewindLibItem(_)
+                | ActionUser::MarkAsWatched(_, _)
+                | ActionUser::ToggleNotifications(_)
+                | ActionUser::MarkVideoAsWatched { .. }
+                | ActionUser::MarkSeasonAsWatched { .. } => Effects::none().unchanged(),
             },
             // Handling msgs that result effects
             Msg::Internal(CtxAddonsPulled(key, addons))
@@ -182,20 +492,23 @@ impl<Env: Environment + 'static> Update for Ctx<Env> {
         };
 
         fx.join(self.library.update::<Env>(&self.content, msg))
+            .join(self.addon_cache.update::<Env>(msg))
+            .join(self.lib_sync.update::<Env>(&self.content, msg))
     }
 }
 
-fn load_storage<Env: Environment>() -> Effect {
+fn load_storage<Env: Environment + 'static>() -> Effect {
     Box::new(
-        Env::get_storage(USER_DATA_KEY)
-            .map(|x| Msg::Internal(CtxLoaded(x)))
+        migrate_storage::<Env>()
+            .and_then(|_| get_storage_or_recover::<Env, _>(USER_DATA_SLOT, CtxContent::recover))
+            .map(|x| Msg::Internal(CtxLoaded(x.map(Box::new))))
             .map_err(|e| Msg::Event(CtxFatal(e.into()))),
     )
 }
 
 fn save_storage<Env: Environment>(content: &CtxContent) -> Effect {
     Box::new(
-        Env::set_storage(USER_DATA_KEY, Some(content))
+        Env::set_storage(USER_DATA_SLOT, Some(content))
             .map(|_| Msg::Event(CtxSaved))
             .map_err(|e| Msg::Event(CtxFatal(e.into()))),
     )
@@ -223,6 +536,92 @@ fn authenticate<Env: Environment + 'static>(action: ActionUser, req: APIRequest)
     Box::new(ft)
 }
 
+// An add-on with the same id or URL is replaced in place, rather than duplicated; it keeps its
+// flags only when it is installed from the same URL
+fn install_addon(addons: &mut Vec<Descriptor>, descriptor: Descriptor) -> Event {
+    let transport_url = descriptor.transport_url.to_owned();
+    let fatal_issues = fatal_manifest_issues(&descriptor.manifest);
+    if !fatal_issues.is_empty() {
+        return AddonInstallFailed(
+            transport_url,
+            AddonManifestError::ManifestInvalid(fatal_issues),
+        );
+    }
+    let is_same_addon = |addon: &Descriptor| {
+        addon.manifest.id == descriptor.manifest.id || addon.transport_url == transport_url
+    };
+    match addons.iter().position(is_same_addon) {
+        Some(position) => {
+            let existing = &addons[position];
+            let from = existing.manifest.version.to_owned();
+            let to = descriptor.manifest.version.to_owned();
+            let is_same_id = existing.manifest.id == descriptor.manifest.id;
+            let is_same_url = existing.transport_url == descriptor.transport_url;
+            if !is_same_url && existing.flags.protected {
+                return AddonInstallFailed(transport_url, AddonManifestError::ReplacesProtected);
+            }
+            // Versions of different add-ons (e.g. a URL which now serves another id) don't compare
+            if is_same_id && to < from {
+                return AddonInstallFailed(transport_url, AddonManifestError::OlderVersion(from));
+            }
+            // The flags belong to the add-on installed from that URL
+            let flags = if is_same_url {
+                existing.flags.to_owned()
+            } else {
+                Default::default()
+            };
+            // Another add-on may match the other one of id and URL; it comes after the first
+            // match, so the position stays the same
+            let mut index = 0;
+            addons.retain(|addon| {
+                let is_duplicate =
+                    index != position && !addon.flags.protected && is_same_addon(addon);
+                index += 1;
+                !is_duplicate
+            });
+            addons[position] = Descriptor {
+                flags,
+                ..descriptor
+            };
+            if is_same_id && to > from {
+                AddonUpgraded(transport_url, from, to)
+            } else {
+                AddonInstalled(transport_url)
+            }
+        }
+        None => {
+            addons.push(descriptor);
+            AddonInstalled(transport_url)
+        }
+    }
+}
+
+fn fatal_manifest_issues(manifest: &Manifest) -> Vec<ManifestIssue> {
+    manifest
+        .validate()
+        .into_iter()
+        .filter(ManifestIssue::is_fatal)
+        .collect()
+}
+
+// Returns the versions (from, to) if the add-on was upgraded; a different add-on
+// served from the same URL is not an upgrade, since it has to be installed explicitly
+fn upgrade_manifest(
+    addon: &mut Descriptor,
+    manifest: &Manifest,
+) -> Result<Option<(Version, Version)>, AddonManifestError> {
+    if manifest.id != addon.manifest.id || manifest.version <= addon.manifest.version {
+        return Ok(None);
+    }
+    let fatal_issues = fatal_manifest_issues(manifest);
+    if !fatal_issues.is_empty() {
+        return Err(AddonManifestError::ManifestInvalid(fatal_issues));
+    }
+    let from = addon.manifest.version.to_owned();
+    addon.manifest = manifest.to_owned();
+    Ok(Some((from, manifest.version.to_owned())))
+}
+
 fn addons_upgrade_local(defaults: &[Descriptor], addons: &[Descriptor]) -> Vec<Descriptor> {
     addons
         .iter()
@@ -0,0 +1,113 @@
+use super::addons::*;
+use crate::state_types::*;
+use crate::types::addons::{AggrRequest, ResourceRef};
+use crate::types::{LibItem, MetaDetail, Stream};
+use serde_derive::*;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DetailSelected {
+    pub type_name: String,
+    pub id: String,
+    pub video_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Detail {
+    pub selected: Option<DetailSelected>,
+    pub metas: Vec<ItemsGroup<MetaDetail>>,
+    // Streams are only requested once a video is selected;
+    // for movies, the video_id is usually the same as the id
+    pub streams: Vec<ItemsGroup<Vec<Stream>>>,
+    pub lib_item: Option<LibItem>,
+    // The ids of the watched videos (episodes), according to the first meta that's ready
+    pub watched_videos: Vec<String>,
+    // Reset on every load, which cancels the effects of the previous one
+    #[serde(skip)]
+    load_effects: EffectsHandle,
+}
+impl<Env: Environment + 'static> UpdateWithCtx<Ctx<Env>> for Detail {
+    fn update(&mut self, ctx: &Ctx<Env>, msg: &Msg) -> Effects {
+        let fx = match msg {
+            Msg::Action(Action::Load(ActionLoad::Detail {
+                type_name,
+                id,
+                video_id,
+            })) => {
+                let meta_ref = ResourceRef::without_extra("meta", type_name, id);
+                let (metas, meta_effects) = addon_aggr_new_cached::<Env, _>(
+                    &ctx.content.addons,
+                    &ctx.addon_cache,
+                    &AggrRequest::AllOfResource(meta_ref),
+                );
+                let (streams, stream_effects) = match video_id {
+                    Some(video_id) => {
+                        let stream_ref = ResourceRef::without_extra("stream", type_name, video_id);
+                        addon_aggr_new_cached::<Env, _>(
+                            &ctx.content.addons,
+                            &ctx.addon_cache,
+                            &AggrRequest::AllOfResource(stream_ref),
+                        )
+                    }
+                    None => (vec![], Effects::none()),
+                };
+                self.load_effects.cancel();
+                *self = Detail {
+                    selected: Some(DetailSelected {
+                        type_name: type_name.to_owned(),
+                        id: id.to_owned(),
+                        video_id: video_id.to_owned(),
+                    }),
+                    metas,
+                    streams,
+                    lib_item: ctx.library.get(id).cloned(),
+                    watched_videos: vec![],
+                    load_effects: EffectsHandle::default(),
+                };
+                meta_effects
+                    .join(stream_effects)
+                    .cancellable(&self.load_effects)
+            }
+            // The library item may change while the Detail is open (e.g. watched from elsewhere)
+            Msg::Event(Event::CtxChanged)
+            | Msg::Internal(Internal::LibLoaded(_))
+            | Msg::Event(Event::LibPersisted) => {
+                let lib_item = self
+                    .selected
+                    .as_ref()
+                    .and_then(|selected| ctx.library.get(&selected.id))
+                    .cloned();
+                if lib_item != self.lib_item {
+                    self.lib_item = lib_item;
+                    Effects::none()
+                } else {
+                    Effects::none().unchanged()
+                }
+            }
+            _ => addon_aggr_update(&mut self.metas, msg)
+                .join(addon_aggr_update(&mut self.streams, msg)),
+        };
+        // Both the metas and the library item may have changed
+        let watched_videos = watched_videos(&self.metas, &self.lib_item);
+        if watched_videos != self.watched_videos {
+            self.watched_videos = watched_videos;
+            fx.join(Effects::none())
+        } else {
+            fx
+        }
+    }
+}
+
+fn watched_videos(metas: &[ItemsGroup<MetaDetail>], lib_item: &Option<LibItem>) -> Vec<String> {
+    let meta = metas.iter().find_map(|group| match &group.content {
+        Loadable::Ready(meta) => Some(meta),
+        _ => None,
+    });
+    match (meta, lib_item) {
+        (Some(meta), Some(lib_item)) => lib_item
+            .watched_bitfield(&meta.videos)
+            .watched_videos()
+            .cloned()
+            .collect(),
+        _ => vec![],
+    }
+}
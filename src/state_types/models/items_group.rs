@@ -8,8 +8,18 @@ use std::convert::TryInto;
 pub enum CatalogError {
     EmptyContent,
     UnexpectedResp,
+    Timeout,
     Other(String),
 }
+impl From<&EnvError> for CatalogError {
+    fn from(e: &EnvError) -> Self {
+        if e.is::<TimeoutError>() {
+            CatalogError::Timeout
+        } else {
+            CatalogError::Other(e.to_string())
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(tag = "type", content = "content")]
@@ -47,7 +57,7 @@ where
                 Ok(x) => Loadable::Ready(x),
                 Err(_) => Loadable::Err(CatalogError::UnexpectedResp),
             },
-            Err(e) => Loadable::Err(CatalogError::Other(e.to_string())),
+            Err(e) => Loadable::Err(e.into()),
         };
     }
     fn addon_req(&self) -> &ResourceRequest {
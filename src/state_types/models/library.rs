@@ -2,16 +2,21 @@ use crate::state_types::Event::*;
 use crate::state_types::Internal::*;
 use crate::state_types::*;
 use crate::types::api::*;
-use crate::types::{LibBucket, LibItem, LibItemModified, LIB_RECENT_COUNT, UID};
+use crate::types::{
+    LibBucket, LibItem, LibItemModified, MetaDetail, Video, WatchedState, LIB_RECENT_COUNT, UID,
+};
 use derivative::*;
 use enclose::*;
 use futures::future::Either;
 use futures::{future, Future};
 use lazysort::SortedBy;
+use std::time::Duration;
 
 const COLL_NAME: &str = "libraryItem";
-const STORAGE_RECENT_SLOT: &str = "recent_library";
-const STORAGE_SLOT: &str = "library";
+const SYNC_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// Doubled after every failed attempt
+const RETRY_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_RETRIES: u32 = 6;
 
 #[derive(Derivative, PartialEq)]
 #[derivative(Debug, Default, Clone)]
@@ -38,8 +43,8 @@ impl LibraryLoadable {
         *self = LibraryLoadable::Loading(uid.to_owned());
 
         let mut default_bucket = LibBucket::new(uid, vec![]);
-        let ft = Env::get_storage::<LibBucket>(STORAGE_SLOT)
-            .join(Env::get_storage::<LibBucket>(STORAGE_RECENT_SLOT))
+        let ft = get_storage_or_backup::<Env, LibBucket>(LIBRARY_SLOT)
+            .join(get_storage_or_backup::<Env, LibBucket>(LIBRARY_RECENT_SLOT))
             .map(move |(a, b)| {
                 for loaded_bucket in a.into_iter().chain(b.into_iter()) {
                     default_bucket.try_merge(loaded_bucket);
@@ -110,7 +115,18 @@ impl LibraryLoadable {
                                     Effects::none().unchanged()
                                 }
                             }
-                            ActionUser::LibUpdate(item) => {
+                            ActionUser::LibUpdate(_)
+                            | ActionUser::AddToLibrary(_)
+                            | ActionUser::RemoveFromLibrary(_)
+                            | ActionUser::RewindLibItem(_)
+                            | ActionUser::MarkAsWatched(_, _)
+                            | ActionUser::ToggleNotifications(_)
+                            | ActionUser::MarkVideoAsWatched { .. }
+                            | ActionUser::MarkSeasonAsWatched { .. } => {
+                                let item = match updated_lib_item::<Env>(lib_bucket, action) {
+                                    Some(item) => item,
+                                    None => return Effects::none().unchanged(),
+                                };
                                 // The item needs to have a newer mtime than the item with the same
                                 // ID (if any), otherwise it won't be merged in the lib_bucket
                                 let new_bucket = LibBucket::new(
@@ -119,16 +135,21 @@ impl LibraryLoadable {
                                 );
                                 let persist_ft = update_and_persist::<Env>(lib_bucket, new_bucket)
                                     .map(|_| LibPersisted.into())
-                                    .map_err(err_mapper.clone());
+                                    .map_err(err_mapper);
 
-                                // If we're logged in, push to API
-                                if let Some(auth) = &content.auth {
-                                    let push_ft = lib_push::<Env>(auth, &item)
-                                        .map(|_| LibPushed.into())
-                                        .map_err(err_mapper);
-                                    Effects::many(vec![Box::new(persist_ft), Box::new(push_ft)])
-                                } else {
-                                    Effects::one(Box::new(persist_ft))
+                                // If we're logged in, push to API; some items (e.g. temp ones) are
+                                // never pushed, but unlike in lib_sync, removals always are
+                                match &content.auth {
+                                    Some(auth) if item.can_push() => {
+                                        // If that fails, LibSync retries it later
+                                        let failed =
+                                            LibBucket::new(Some(auth).into(), vec![item.clone()]);
+                                        let push_ft = lib_push::<Env>(auth, vec![item])
+                                            .map(|_| LibPushed.into())
+                                            .map_err(move |_| LibPushFailed(failed).into());
+                                        Effects::many(vec![Box::new(persist_ft), Box::new(push_ft)])
+                                    }
+                                    _ => Effects::one(Box::new(persist_ft)),
                                 }
                             }
                             _ => Effects::none().unchanged(),
@@ -149,6 +170,234 @@ impl LibraryLoadable {
     }
 }
 
+// The item which the action results in, with a new mtime; None if the item is not in the library
+fn updated_lib_item<Env: Environment>(bucket: &LibBucket, action: &ActionUser) -> Option<LibItem> {
+    let now = Env::now();
+    // Even if the clock didn't move (or went back), the change must win in LibBucket::try_merge
+    let next_mtime = |item: &LibItem| now.max(item.mtime + chrono::Duration::milliseconds(1));
+    let modify = |id: &str, f: &dyn Fn(&mut LibItem)| {
+        bucket.items.get(id).cloned().map(|mut item| {
+            f(&mut item);
+            item.mtime = next_mtime(&item);
+            item
+        })
+    };
+    match action {
+        ActionUser::LibUpdate(item) => Some(item.to_owned()),
+        ActionUser::AddToLibrary(meta) => match bucket.items.get(&meta.id) {
+            // Adding an item which was removed (or only temporarily in the library)
+            // keeps the watch progress
+            Some(item) => Some(LibItem {
+                ctime: item.ctime.or(Some(now)),
+                mtime: next_mtime(item),
+                state: item.state.to_owned(),
+                ..LibItem::from_meta(meta, now)
+            }),
+            None => Some(LibItem::from_meta(meta, now)),
+        },
+        ActionUser::RemoveFromLibrary(id) => modify(id, &|item| item.removed = true),
+        ActionUser::RewindLibItem(id) => modify(id, &|item| item.state.time_offset = 0),
+        ActionUser::MarkAsWatched(id, is_watched) => modify(id, &|item| {
+            if *is_watched {
+                item.state.flagged_watched = 1;
+                item.state.times_watched = item.state.times_watched.max(1);
+                item.state.last_watched = Some(now);
+            } else {
+                item.state.flagged_watched = 0;
+                item.state.times_watched = 0;
+            }
+        }),
+        ActionUser::ToggleNotifications(id) => modify(id, &|item| {
+            item.state.no_notif = !item.state.no_notif;
+        }),
+        ActionUser::MarkVideoAsWatched {
+            meta,
+            video_id,
+            is_watched,
+        } if is_watched_known(bucket, meta) => modify(&meta.id, &|item| {
+            let videos = meta.videos.iter().filter(|video| &video.id == video_id);
+            mark_videos_watched(item, &meta.videos, videos, *is_watched);
+        }),
+        ActionUser::MarkSeasonAsWatched {
+            meta,
+            season,
+            is_watched,
+        } if is_watched_known(bucket, meta) => modify(&meta.id, &|item| {
+            let videos = meta.videos.iter().filter(|video| {
+                video.series_info.as_ref().map(|info| info.season) == Some(*season)
+            });
+            mark_videos_watched(item, &meta.videos, videos, *is_watched);
+        }),
+        _ => None,
+    }
+}
+
+// If the anchor video of the watched field is gone, we can't tell which videos are watched;
+// marking videos would then overwrite the field, so it's kept as it is
+fn is_watched_known(bucket: &LibBucket, meta: &MetaDetail) -> bool {
+    match bucket
+        .items
+        .get(&meta.id)
+        .and_then(|item| item.state.watched.as_ref())
+    {
+        Some(WatchedState::Parsed(field)) => meta
+            .videos
+            .iter()
+            .any(|video| video.id == field.anchor_video),
+        _ => true,
+    }
+}
+
+fn mark_videos_watched<'a>(
+    item: &mut LibItem,
+    all_videos: &[Video],
+    videos: impl Iterator<Item = &'a Video>,
+    is_watched: bool,
+) {
+    let mut bitfield = item.watched_bitfield(all_videos);
+    for video in videos {
+        bitfield.set_watched(&video.id, is_watched);
+    }
+    item.state.watched = bitfield.to_field().map(WatchedState::Parsed);
+}
+
+// Syncing in the background, and retrying the pushes which failed (e.g. while offline);
+// those items are kept in an outbox, which is persisted, so that they are not lost on restart
+#[derive(Debug, Default, Clone)]
+pub struct LibSync {
+    // Only for the user in outbox.uid; the newest change of every item
+    pub outbox: LibBucket,
+    // Failed pushes of the outbox in a row, which determine the backoff
+    failed_attempts: u32,
+    is_retry_scheduled: bool,
+    // Whether the library was just fetched in full, which makes the first sync pointless
+    is_fetched: bool,
+    // Reset whenever the user changes, which cancels the timers
+    timers: EffectsHandle,
+}
+
+impl LibSync {
+    pub fn load_from_storage<Env: Environment + 'static>(&self) -> Effects {
+        let ft = get_storage_or_backup::<Env, LibBucket>(LIBRARY_OUTBOX_SLOT)
+            .map(|outbox| LibOutboxLoaded(outbox.unwrap_or_default()).into())
+            .map_err(|e| LibFatal(e.into()).into());
+        Effects::one(Box::new(ft)).unchanged()
+    }
+    pub fn update<Env: Environment + 'static>(
+        &mut self,
+        content: &CtxContent,
+        msg: &Msg,
+    ) -> Effects {
+        match msg {
+            Msg::Internal(LibOutboxLoaded(outbox)) => {
+                // The outbox of another user can't be pushed anymore
+                if outbox.uid == content.auth.as_ref().into() {
+                    // Anything that failed in the meantime is newer
+                    let failed = std::mem::replace(&mut self.outbox, outbox.to_owned());
+                    if failed.uid == self.outbox.uid {
+                        self.outbox.try_merge(failed);
+                    } else if !failed.items.is_empty() {
+                        self.outbox = failed;
+                    }
+                }
+                self.flush::<Env>(content)
+            }
+            Msg::Internal(CtxUpdate(_)) => {
+                self.timers.reset();
+                self.is_retry_scheduled = false;
+                // The library is fetched in full, see LibraryLoadable::load_initial
+                self.is_fetched = content.auth.is_some();
+                Effects::none().unchanged()
+            }
+            // Loaded from storage or from the API, after logging in
+            Msg::Internal(LibLoaded(_)) | Msg::Internal(LibSyncScheduled)
+                if content.auth.is_some() =>
+            {
+                if let Msg::Internal(LibLoaded(_)) = msg {
+                    self.timers.reset();
+                    self.is_retry_scheduled = false;
+                }
+                self.failed_attempts = 0;
+                let next_sync = Env::delay(SYNC_INTERVAL)
+                    .map(|_| LibSyncScheduled.into())
+                    .map_err(|_| EffectCancelled.into());
+                // Right after a full fetch, there's nothing to sync
+                let sync = if std::mem::replace(&mut self.is_fetched, false) {
+                    Effects::none()
+                } else {
+                    Effects::msg(Action::UserOp(ActionUser::LibSync).into())
+                };
+                sync.join(Effects::one(Box::new(next_sync)).cancellable(&self.timers))
+                    .join(self.flush::<Env>(content))
+                    .unchanged()
+            }
+            Msg::Internal(LibPushFailed(failed)) => {
+                if self.outbox.uid != failed.uid {
+                    // The outbox of a previous user can't be pushed anymore
+                    self.outbox = LibBucket::new(failed.uid.to_owned(), vec![]);
+                }
+                self.outbox.try_merge(failed.to_owned());
+                self.persist::<Env>().join(self.schedule_retry::<Env>())
+            }
+            Msg::Internal(LibOutboxRetry) => {
+                self.is_retry_scheduled = false;
+                self.flush::<Env>(content)
+            }
+            Msg::Internal(LibOutboxPushed(pushed)) => {
+                self.failed_attempts = 0;
+                // Items which changed again while pushing stay in the outbox
+                self.outbox
+                    .items
+                    .retain(|id, item| match pushed.items.get(id) {
+                        Some(pushed) => pushed.mtime < item.mtime,
+                        None => true,
+                    });
+                self.persist::<Env>()
+            }
+            Msg::Internal(LibOutboxPushFailed) => {
+                self.failed_attempts += 1;
+                self.schedule_retry::<Env>()
+            }
+            _ => Effects::none().unchanged(),
+        }
+    }
+    fn flush<Env: Environment + 'static>(&self, content: &CtxContent) -> Effects {
+        match &content.auth {
+            Some(auth)
+                if !self.outbox.items.is_empty() && self.outbox.uid == UID::from(Some(auth)) =>
+            {
+                let pushed = self.outbox.to_owned();
+                let items = pushed.items.values().cloned().collect();
+                let ft = lib_push::<Env>(auth, items)
+                    .map(|_| LibOutboxPushed(pushed).into())
+                    .map_err(|_| LibOutboxPushFailed.into());
+                Effects::one(Box::new(ft)).unchanged()
+            }
+            _ => Effects::none().unchanged(),
+        }
+    }
+    // Exponential backoff; after MAX_RETRIES, the outbox waits for the next sync
+    fn schedule_retry<Env: Environment + 'static>(&mut self) -> Effects {
+        if self.is_retry_scheduled || self.failed_attempts >= MAX_RETRIES {
+            return Effects::none().unchanged();
+        }
+        self.is_retry_scheduled = true;
+        let backoff = RETRY_BACKOFF * 2u32.pow(self.failed_attempts);
+        let ft = Env::delay(backoff)
+            .map(|_| LibOutboxRetry.into())
+            .map_err(|_| EffectCancelled.into());
+        Effects::one(Box::new(ft))
+            .cancellable(&self.timers)
+            .unchanged()
+    }
+    fn persist<Env: Environment + 'static>(&self) -> Effects {
+        let ft = Env::set_storage(LIBRARY_OUTBOX_SLOT, Some(&self.outbox))
+            .map(|_| LibOutboxPersisted.into())
+            .map_err(|e| LibFatal(e.into()).into());
+        Effects::one(Box::new(ft)).unchanged()
+    }
+}
+
 fn datastore_req_builder(auth: &Auth) -> DatastoreReqBuilder {
     DatastoreReqBuilder::default()
         .auth_key(auth.key.to_owned())
@@ -219,11 +468,9 @@ fn lib_sync<Env: Environment + 'static>(
 
 fn lib_push<Env: Environment + 'static>(
     auth: &Auth,
-    item: &LibItem,
+    items: Vec<LibItem>,
 ) -> impl Future<Item = (), Error = CtxError> {
-    let push_req = datastore_req_builder(auth).with_cmd(DatastoreCmd::Put {
-        changes: vec![item.to_owned()],
-    });
+    let push_req = datastore_req_builder(auth).with_cmd(DatastoreCmd::Put { changes: items });
 
     api_fetch::<Env, SuccessResponse, _>(push_req).map(|_| ())
 }
@@ -258,15 +505,15 @@ fn update_and_persist<Env: Environment + 'static>(
     // otherwise, we will only save the recent bucket if all of the modified items were previously
     // in the recent bucket;
     if bucket.items.len() <= LIB_RECENT_COUNT {
-        Either::A(Env::set_storage(STORAGE_RECENT_SLOT, Some(bucket)).map_err(Into::into))
+        Either::A(Env::set_storage(LIBRARY_RECENT_SLOT, Some(bucket)).map_err(Into::into))
     } else {
         let (recent, other) = bucket.split_by_recent();
         if new_were_in_recent {
-            Either::A(Env::set_storage(STORAGE_RECENT_SLOT, Some(&recent)).map_err(Into::into))
+            Either::A(Env::set_storage(LIBRARY_RECENT_SLOT, Some(&recent)).map_err(Into::into))
         } else {
             Either::B(
-                Env::set_storage(STORAGE_RECENT_SLOT, Some(&recent))
-                    .join(Env::set_storage(STORAGE_SLOT, Some(&other)))
+                Env::set_storage(LIBRARY_RECENT_SLOT, Some(&recent))
+                    .join(Env::set_storage(LIBRARY_SLOT, Some(&other)))
                     .map(|(_, _)| ())
                     .map_err(Into::into),
             )
@@ -0,0 +1,149 @@
+use super::{Ctx, LibraryLoadable};
+use crate::state_types::*;
+use crate::types::{LibBucket, LibItem};
+use itertools::Itertools;
+use serde_derive::*;
+use std::cmp::{Ordering, Reverse};
+
+const PAGE_LEN: usize = 100;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum LibrarySort {
+    #[default]
+    LastWatched,
+    Name,
+    // When the item was added to the library (ctime)
+    DateAdded,
+    TimesWatched,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct LibraryRequest {
+    // All types if None
+    pub type_name: Option<String>,
+    #[serde(default)]
+    pub sort: LibrarySort,
+    // Starting from 0
+    #[serde(default)]
+    pub page: usize,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct LibraryTypeEntry {
+    pub is_selected: bool,
+    pub type_name: String,
+    pub load: LibraryRequest,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LibraryFiltered {
+    pub selected: Option<LibraryRequest>,
+    // The types of the items in the library, in alphabetical order
+    pub types: Vec<LibraryTypeEntry>,
+    pub items: Vec<LibItem>,
+    // Pagination: loading previous/next pages
+    pub load_next: Option<LibraryRequest>,
+    pub load_prev: Option<LibraryRequest>,
+}
+impl<Env: Environment + 'static> UpdateWithCtx<Ctx<Env>> for LibraryFiltered {
+    fn update(&mut self, ctx: &Ctx<Env>, msg: &Msg) -> Effects {
+        match msg {
+            Msg::Action(Action::Load(ActionLoad::LibraryFiltered(req))) => {
+                self.selected = Some(req.to_owned());
+                self.update_items(&ctx.library);
+                Effects::none()
+            }
+            Msg::Event(Event::CtxChanged)
+            | Msg::Internal(Internal::LibLoaded(_))
+            | Msg::Event(Event::LibPersisted)
+                if self.selected.is_some() =>
+            {
+                self.update_items(&ctx.library);
+                Effects::none()
+            }
+            _ => Effects::none().unchanged(),
+        }
+    }
+}
+impl LibraryFiltered {
+    fn update_items(&mut self, library: &LibraryLoadable) {
+        let (req, bucket) = match (&self.selected, library) {
+            (Some(req), LibraryLoadable::Ready(bucket)) => (req, bucket),
+            _ => {
+                self.types = vec![];
+                self.items = vec![];
+                self.load_next = None;
+                self.load_prev = None;
+                return;
+            }
+        };
+        self.types = library_items(bucket)
+            .map(|item| item.type_name.to_owned())
+            .unique()
+            .sorted()
+            .map(|type_name| LibraryTypeEntry {
+                is_selected: req.type_name.as_ref() == Some(&type_name),
+                load: LibraryRequest {
+                    type_name: Some(type_name.to_owned()),
+                    sort: req.sort,
+                    page: 0,
+                },
+                type_name,
+            })
+            .collect();
+        let mut items: Vec<&LibItem> = library_items(bucket)
+            .filter(|item| match &req.type_name {
+                Some(type_name) => &item.type_name == type_name,
+                None => true,
+            })
+            .collect();
+        items.sort_by(|a, b| compare_items(req.sort, a, b));
+        let page_start = req.page * PAGE_LEN;
+        self.load_prev = if req.page > 0 {
+            Some(LibraryRequest {
+                page: req.page - 1,
+                ..req.to_owned()
+            })
+        } else {
+            None
+        };
+        self.load_next = if items.len() > page_start + PAGE_LEN {
+            Some(LibraryRequest {
+                page: req.page + 1,
+                ..req.to_owned()
+            })
+        } else {
+            None
+        };
+        self.items = items
+            .into_iter()
+            .skip(page_start)
+            .take(PAGE_LEN)
+            .cloned()
+            .collect();
+    }
+}
+
+// Removed and temporary items are not shown in the library
+fn library_items(bucket: &LibBucket) -> impl Iterator<Item = &LibItem> {
+    bucket
+        .items
+        .values()
+        .filter(|item| !item.removed && !item.temp)
+}
+
+// The newest/most watched first, except for the names; the id makes the order stable,
+// since the items come from a HashMap
+fn compare_items(sort: LibrarySort, a: &LibItem, b: &LibItem) -> Ordering {
+    let ordering = match sort {
+        LibrarySort::LastWatched => {
+            Reverse(a.state.last_watched).cmp(&Reverse(b.state.last_watched))
+        }
+        LibrarySort::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        LibrarySort::DateAdded => Reverse(a.ctime).cmp(&Reverse(b.ctime)),
+        LibrarySort::TimesWatched => {
+            Reverse(a.state.times_watched).cmp(&Reverse(b.state.times_watched))
+        }
+    };
+    ordering.then_with(|| a.id.cmp(&b.id))
+}
@@ -10,15 +10,33 @@ pub use items_group::*;
 mod addons;
 pub use addons::*;
 
+mod addon_cache;
+pub use addon_cache::*;
+
 mod catalogs;
 pub use catalogs::*;
 
+mod addons_discovery;
+pub use addons_discovery::*;
+
 mod streams;
 pub use streams::*;
 
+mod detail;
+pub use detail::*;
+
+mod player;
+pub use player::*;
+
+mod subtitles;
+pub use subtitles::*;
+
 mod lib_recent;
 pub use lib_recent::*;
 
+mod library_filtered;
+pub use library_filtered::*;
+
 mod notifications;
 pub use notifications::*;
 
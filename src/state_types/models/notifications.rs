@@ -14,6 +14,9 @@ const LAST_VID_IDS: &str = "lastVideosIds";
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct Notifications {
     pub groups: Vec<ItemsGroup<Vec<MetaDetail>>>,
+    // Reset on every load, which cancels the effects of the previous one
+    #[serde(skip)]
+    load_effects: EffectsHandle,
 }
 impl<Env: Environment + 'static> UpdateWithCtx<Ctx<Env>> for Notifications {
     fn update(&mut self, ctx: &Ctx<Env>, msg: &Msg) -> Effects {
@@ -75,10 +78,11 @@ impl<Env: Environment + 'static> UpdateWithCtx<Ctx<Env>> for Notifications {
                                     let addon_req =
                                         ResourceRequest::new(&addon.transport_url, path);
 
-                                    (
-                                        addon_get::<Env>(&addon_req),
-                                        ItemsGroup::new(addon, addon_req),
-                                    )
+                                    let mut group = ItemsGroup::new(addon, addon_req);
+                                    let effect =
+                                        addon_get_cached::<Env, _>(&ctx.addon_cache, &mut group);
+                                    retain_new_videos(&mut group, &ctx.library);
+                                    (effect, group)
                                 })
                                 .collect::<Vec<_>>()
                         })
@@ -86,33 +90,14 @@ impl<Env: Environment + 'static> UpdateWithCtx<Ctx<Env>> for Notifications {
                     .unzip();
 
                 self.groups = groups;
-                Effects::many(effects)
+                self.load_effects.reset();
+                Effects::many(effects.into_iter().flatten().collect())
+                    .cancellable(&self.load_effects)
             }
             Msg::Internal(AddonResponse(req, result)) => {
                 if let Some(idx) = self.groups.iter().position(|g| g.addon_req() == req) {
                     self.groups[idx].update(result);
-                    // Modify all the items so that only the new videos are left
-                    if let Loadable::Ready(ref mut meta_items) = self.groups[idx].content {
-                        for item in meta_items {
-                            if let Some(lib_item) = ctx.library.get(&item.id) {
-                                item.videos
-                                    // It's not gonna be a notification if we don't have the
-                                    // released date of the last watched video
-                                    // NOTE: if we want to show the recent videos in the default
-                                    // case, we should unwrap_or(now - THRESHOLD)
-                                    // we have to get `now` somehow (environment or through a msg)
-                                    // Alternatively, just set that when we add lib items
-                                    .retain(|v| {
-                                        lib_item
-                                            .state
-                                            .last_vid_released
-                                            .map_or(false, |lvr| v.released > lvr)
-                                    });
-                            } else {
-                                item.videos = vec![];
-                            }
-                        }
-                    }
+                    retain_new_videos(&mut self.groups[idx], &ctx.library);
                     Effects::none()
                 } else {
                     Effects::none().unchanged()
@@ -122,3 +107,28 @@ impl<Env: Environment + 'static> UpdateWithCtx<Ctx<Env>> for Notifications {
         }
     }
 }
+
+// Modify all the items so that only the new videos are left
+fn retain_new_videos(group: &mut ItemsGroup<Vec<MetaDetail>>, library: &LibraryLoadable) {
+    if let Loadable::Ready(ref mut meta_items) = group.content {
+        for item in meta_items {
+            if let Some(lib_item) = library.get(&item.id) {
+                item.videos
+                    // It's not gonna be a notification if we don't have the
+                    // released date of the last watched video
+                    // NOTE: if we want to show the recent videos in the default
+                    // case, we should unwrap_or(now - THRESHOLD)
+                    // we have to get `now` somehow (environment or through a msg)
+                    // Alternatively, just set that when we add lib items
+                    .retain(|v| {
+                        lib_item
+                            .state
+                            .last_vid_released
+                            .map_or(false, |lvr| v.released > lvr)
+                    });
+            } else {
+                item.videos = vec![];
+            }
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use super::addons::*;
+use crate::state_types::*;
+use crate::types::addons::{AggrRequest, ResourceRef};
+use crate::types::{LibItem, MetaDetail, Stream, Video, WATCHED_THRESHOLD_COEF};
+use serde_derive::*;
+
+// How much playback (in milliseconds) we accumulate before sending the library item
+// through ActionUser::LibUpdate; pausing and reaching the watched threshold send it right away
+const LIB_UPDATE_INTERVAL: u64 = 30_000;
+// Players report the time a few times per second; bigger jumps than that are seeking, not watching
+const MAX_WATCHED_DELTA: u64 = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlayerSelected {
+    pub type_name: String,
+    pub id: String,
+    pub video_id: Option<String>,
+    pub stream: Stream,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Player {
+    pub selected: Option<PlayerSelected>,
+    pub is_loaded: bool,
+    pub error: Option<String>,
+    // Those are None until the player reports them
+    pub time: Option<u64>,
+    pub duration: Option<u64>,
+    pub volume: Option<u8>,
+    pub paused: Option<bool>,
+    // Items which are not in the library are tracked in a temporary one (see set_temp_lib_item)
+    pub lib_item: Option<LibItem>,
+    // For the next video, and for the temporary library item
+    pub metas: Vec<ItemsGroup<MetaDetail>>,
+    pub next_video: Option<Video>,
+    // A stream of the next video from the same binge group as the current stream, if any;
+    // it's autoplayed when the video ends, if Settings::autoplay_next_vid is on
+    pub next_stream: Option<Stream>,
+    #[serde(skip)]
+    next_streams: Vec<ItemsGroup<Vec<Stream>>>,
+    #[serde(skip)]
+    unpushed_time: u64,
+    // Reset on every load, which cancels the effects of the previous one
+    #[serde(skip)]
+    load_effects: EffectsHandle,
+}
+impl<Env: Environment + 'static> UpdateWithCtx<Ctx<Env>> for Player {
+    fn update(&mut self, ctx: &Ctx<Env>, msg: &Msg) -> Effects {
+        match msg {
+            Msg::Action(Action::Load(ActionLoad::Player {
+                type_name,
+                id,
+                video_id,
+                stream,
+            })) => {
+                // Make sure we don't lose the progress on whatever was playing before
+                let flush_effects = self.push_lib_item();
+                let lib_item = ctx.library.get(id).cloned().map(|mut lib_item| {
+                    // Watching another video of the same item starts over
+                    if &lib_item.state.video_id != video_id {
+                        lib_item.state.time_watched = 0;
+                        lib_item.state.time_offset = 0;
+                    }
+                    lib_item
+                });
+                let (metas, meta_effects) = addon_aggr_new::<Env, _>(
+                    &ctx.content.addons,
+                    &AggrRequest::AllOfResource(ResourceRef::without_extra("meta", type_name, id)),
+                );
+                self.load_effects.cancel();
+                *self = Player {
+                    selected: Some(PlayerSelected {
+                        type_name: type_name.to_owned(),
+                        id: id.to_owned(),
+                        video_id: video_id.to_owned(),
+                        stream: *stream.to_owned(),
+                    }),
+                    lib_item,
+                    metas,
+                    ..Default::default()
+                };
+                flush_effects.join(meta_effects.cancellable(&self.load_effects))
+            }
+            // Leaving the player (e.g. for another route) must not lose the progress
+            Msg::Action(Action::Unload) | Msg::Action(Action::Load(_))
+                if self.selected.is_some() =>
+            {
+                let flush_effects = self.push_lib_item();
+                self.load_effects.cancel();
+                *self = Player::default();
+                flush_effects
+            }
+            Msg::Action(Action::PlayerEvent(event)) if self.selected.is_some() => match event {
+                PlayerEvent::Loaded => {
+                    self.is_loaded = true;
+                    Effects::none()
+                }
+                PlayerEvent::Error(error) => {
+                    self.error = Some(error.to_owned());
+                    Effects::none()
+                }
+                PlayerEvent::PropChanged(prop) | PlayerEvent::PropValue(prop) => {
+                    self.update_prop::<Env>(prop)
+                }
+                PlayerEvent::Ended => self.ended::<Env>(ctx),
+            },
+            // The frontend executes those as well, but the state is updated right away
+            Msg::Action(Action::PlayerOp(PlayerAction::SetProp(prop)))
+                if self.selected.is_some() =>
+            {
+                match prop {
+                    // Seeking is not watching
+                    PlayerProp::Time(time) => {
+                        self.time = Some(*time);
+                        self.update_lib_item::<Env>(0)
+                    }
+                    _ => self.update_prop::<Env>(prop),
+                }
+            }
+            Msg::Internal(Internal::AddonResponse(..)) if self.selected.is_some() => {
+                let fx = addon_aggr_update(&mut self.metas, msg)
+                    .join(addon_aggr_update(&mut self.next_streams, msg));
+                if fx.has_changed {
+                    self.set_temp_lib_item::<Env>();
+                    fx.join(self.update_next::<Env>(ctx))
+                } else {
+                    fx
+                }
+            }
+            _ => Effects::none().unchanged(),
+        }
+    }
+}
+
+impl Player {
+    fn update_prop<Env: Environment>(&mut self, prop: &PlayerProp) -> Effects {
+        match prop {
+            PlayerProp::Time(time) => {
+                let prev_time = self.time.replace(*time);
+                let is_playing = self.paused != Some(true);
+                let watched_delta = match prev_time {
+                    Some(prev_time)
+                        if is_playing
+                            && *time > prev_time
+                            && *time - prev_time <= MAX_WATCHED_DELTA =>
+                    {
+                        *time - prev_time
+                    }
+                    _ => 0,
+                };
+                self.update_lib_item::<Env>(watched_delta)
+            }
+            PlayerProp::Duration(duration) => {
+                self.duration = Some(*duration);
+                Effects::none()
+            }
+            PlayerProp::Volume(volume) => {
+                self.volume = Some(*volume);
+                Effects::none()
+            }
+            PlayerProp::Paused(paused) => {
+                self.paused = Some(*paused);
+                if *paused {
+                    self.push_lib_item()
+                } else {
+                    Effects::none()
+                }
+            }
+        }
+    }
+    fn update_lib_item<Env: Environment>(&mut self, watched_delta: u64) -> Effects {
+        let (selected, lib_item) = match (&self.selected, &mut self.lib_item) {
+            (Some(selected), Some(lib_item)) => (selected, lib_item),
+            _ => return Effects::none(),
+        };
+        let now = Env::now();
+        let state = &mut lib_item.state;
+        let was_watched = is_over_watched_threshold(state.time_watched, state.duration);
+        state.last_watched = Some(now);
+        state.video_id = selected.video_id.to_owned();
+        state.time_offset = self.time.unwrap_or(0);
+        state.duration = self.duration.unwrap_or(state.duration);
+        state.time_watched += watched_delta;
+        state.overall_time_watched += watched_delta;
+        let is_watched = is_over_watched_threshold(state.time_watched, state.duration);
+        if is_watched && !was_watched {
+            state.times_watched += 1;
+        }
+        lib_item.mtime = now;
+
+        self.unpushed_time += watched_delta;
+        if (is_watched && !was_watched) || self.unpushed_time >= LIB_UPDATE_INTERVAL {
+            self.push_lib_item()
+        } else {
+            Effects::none()
+        }
+    }
+    fn update_next<Env: Environment + 'static>(&mut self, ctx: &Ctx<Env>) -> Effects {
+        let selected = match &self.selected {
+            Some(selected) => selected,
+            None => return Effects::none().unchanged(),
+        };
+        let next_video = selected.video_id.as_ref().and_then(|video_id| {
+            self.metas
+                .iter()
+                .find_map(|group| match &group.content {
+                    Loadable::Ready(meta) => meta.next_video(video_id),
+                    _ => None,
+                })
+                .cloned()
+        });
+        // Only streams from the same binge group can be picked
+        let binge_group = &selected.stream.behavior_hints.binge_group;
+        let effects = if next_video != self.next_video {
+            let (next_streams, effects) = match (&next_video, binge_group) {
+                (Some(next_video), Some(_)) => addon_aggr_new::<Env, _>(
+                    &ctx.content.addons,
+                    &AggrRequest::AllOfResource(ResourceRef::without_extra(
+                        "stream",
+                        &selected.type_name,
+                        &next_video.id,
+                    )),
+                ),
+                _ => (vec![], Effects::none()),
+            };
+            self.next_video = next_video;
+            self.next_streams = next_streams;
+            effects.cancellable(&self.load_effects)
+        } else {
+            Effects::none().unchanged()
+        };
+        // Streams which come with the video itself are preferred
+        let next_stream = match (&self.next_video, binge_group) {
+            (Some(next_video), Some(_)) => next_video
+                .streams
+                .iter()
+                .chain(
+                    self.next_streams
+                        .iter()
+                        .flat_map(|group| match &group.content {
+                            Loadable::Ready(streams) => streams.iter().collect(),
+                            _ => vec![],
+                        }),
+                )
+                .find(|stream| &stream.behavior_hints.binge_group == binge_group)
+                .cloned(),
+            _ => None,
+        };
+        self.next_stream = next_stream;
+        effects
+    }
+    // The next video becomes the video_id of the library item, which puts it in "continue watching"
+    fn ended<Env: Environment>(&mut self, ctx: &Ctx<Env>) -> Effects {
+        let (selected, next_video) = match (&self.selected, &self.next_video) {
+            (Some(selected), Some(next_video)) => (selected, next_video),
+            _ => return self.push_lib_item(),
+        };
+        if let Some(lib_item) = &mut self.lib_item {
+            lib_item.state.video_id = Some(next_video.id.to_owned());
+            lib_item.state.time_watched = 0;
+            lib_item.state.time_offset = 0;
+            lib_item.mtime = Env::now();
+        }
+        match &self.next_stream {
+            // Loading the next video pushes the library item as well
+            Some(next_stream) if ctx.content.settings.autoplay_next_vid => Effects::msg(
+                Action::Load(ActionLoad::Player {
+                    type_name: selected.type_name.to_owned(),
+                    id: selected.id.to_owned(),
+                    video_id: Some(next_video.id.to_owned()),
+                    stream: Box::new(next_stream.to_owned()),
+                })
+                .into(),
+            )
+            .unchanged(),
+            _ => self.push_lib_item(),
+        }
+    }
+    // Items which are not in the library are added as removed and temp, which only shows them
+    // in continue watching; the meta is what they're built from
+    fn set_temp_lib_item<Env: Environment>(&mut self) {
+        if self.lib_item.is_some() {
+            return;
+        }
+        let meta = self.metas.iter().find_map(|group| match &group.content {
+            Loadable::Ready(meta) => Some(meta),
+            _ => None,
+        });
+        if let Some(meta) = meta {
+            self.lib_item = Some(LibItem {
+                removed: true,
+                temp: true,
+                ..LibItem::from_meta(meta, Env::now())
+            });
+        }
+    }
+    fn push_lib_item(&mut self) -> Effects {
+        self.unpushed_time = 0;
+        match &self.lib_item {
+            Some(lib_item) => {
+                Effects::msg(Action::UserOp(ActionUser::LibUpdate(lib_item.to_owned())).into())
+            }
+            None => Effects::none(),
+        }
+    }
+}
+
+fn is_over_watched_threshold(time_watched: u64, duration: u64) -> bool {
+    duration > 0 && time_watched as f64 > duration as f64 * WATCHED_THRESHOLD_COEF
+}
@@ -3,12 +3,13 @@ use crate::state_types::msg::Internal::{
 };
 use crate::state_types::msg::{Action, ActionSettings};
 use crate::state_types::{Ctx, Effects, Environment, Event, Msg, Request, UpdateWithCtx};
+use crate::types::{Language, StreamQuality};
 use futures::future::Future;
 use lazy_static::lazy_static;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 use std::fmt::{self, Debug};
-use std::path::Path;
+use url::Url;
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct SsOption {
@@ -76,7 +77,6 @@ impl Default for SsValues {
         }
     }
 }
-
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct SsSettings {
@@ -85,56 +85,235 @@ pub struct SsSettings {
     pub base_url: String,
 }
 
+const SUBTITLES_SIZE_MIN: u16 = 50;
+const SUBTITLES_SIZE_MAX: u16 = 300;
+
 // These are the user settings from local storage.
+// Every field falls back to its default, so that adding a field doesn't break loading the ctx;
+// when loading them, invalid fields fall back to their defaults as well, see deserialize_stored_settings
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
 pub struct Settings {
-    pub language: String,
-    pub subtitles_size: String,
-    pub subtitles_language: String,
-    pub subtitles_background: String,
-    pub subtitles_color: String,
-    pub subtitles_outline_color: String,
-    pub autoplay_next_vid: String,
-    pub server_url: String,
-    pub use_external_player: String,
+    pub language: Language,
+    pub subtitles_size: Percentage,
+    pub subtitles_language: Language,
+    pub subtitles_background: Option<Color>,
+    pub subtitles_color: Color,
+    pub subtitles_outline_color: Color,
+    pub autoplay_next_vid: bool,
+    #[serde(with = "url_string")]
+    pub server_url: Url,
+    pub use_external_player: bool,
     // We can't override Esc in browser so this option is pointless here
-    // pub player_esc_exits_fullscreen:  String,
-    pub pause_on_lost_focus: String,
-    pub show_vid_overview: String,
+    // pub player_esc_exits_fullscreen: bool,
+    pub pause_on_lost_focus: bool,
+    pub show_vid_overview: bool,
+    pub stream_ranking: StreamRankingPrefs,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Percentage(pub u16);
+
+// A CSS hex colour: #rgb, #rgba, #rrggbb or #rrggbbaa
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Color(pub String);
+impl Color {
+    pub fn is_valid(&self) -> bool {
+        match self.0.strip_prefix('#') {
+            Some(hex) => {
+                [3, 4, 6, 8].contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit())
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "field", content = "value")]
+pub enum SettingsError {
+    SubtitlesSize(u16),
+    SubtitlesBackground(String),
+    SubtitlesColor(String),
+    SubtitlesOutlineColor(String),
+    ServerUrl(String),
+}
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::SubtitlesSize(size) => write!(
+                f,
+                "subtitles size must be between {}% and {}%, got {}%",
+                SUBTITLES_SIZE_MIN, SUBTITLES_SIZE_MAX, size
+            ),
+            SettingsError::SubtitlesBackground(color) => {
+                write!(f, "invalid subtitles background colour: {}", color)
+            }
+            SettingsError::SubtitlesColor(color) => {
+                write!(f, "invalid subtitles colour: {}", color)
+            }
+            SettingsError::SubtitlesOutlineColor(color) => {
+                write!(f, "invalid subtitles outline colour: {}", color)
+            }
+            SettingsError::ServerUrl(url) => {
+                write!(f, "streaming server URL must be http(s): {}", url)
+            }
+        }
+    }
+}
+
+// How the Streams model ranks the streams from all add-ons
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct StreamRankingPrefs {
+    // Web ready streams can be played without the streaming server
+    pub prefer_web_ready: bool,
+    // Otherwise, streams which are not P2P are preferred, since they usually start faster
+    pub prefer_p2p: bool,
+    // Streams of better quality are ranked as if they were of that quality (e.g. for slow connections)
+    pub max_quality: Option<StreamQuality>,
+}
+impl Default for StreamRankingPrefs {
+    fn default() -> Self {
+        StreamRankingPrefs {
+            prefer_web_ready: true,
+            prefer_p2p: false,
+            max_quality: None,
+        }
+    }
 }
 
 impl Settings {
     fn get_endpoint(&self) -> String {
-        Path::new(&self.server_url)
-            .join("settings")
-            .into_os_string()
-            .into_string()
-            .unwrap_or_else(|_| {
-                Path::new(&Settings::default().server_url)
-                    .join("settings")
-                    .into_os_string()
-                    .into_string()
-                    .expect("Default streaming server endpint is broken")
-            })
+        // Url::join would replace the last segment of URLs without a trailing slash
+        let mut url = self.server_url.to_owned();
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.pop_if_empty().push("settings");
+        }
+        url.into_string()
+    }
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let Percentage(size) = self.subtitles_size;
+        if !(SUBTITLES_SIZE_MIN..=SUBTITLES_SIZE_MAX).contains(&size) {
+            return Err(SettingsError::SubtitlesSize(size));
+        }
+        if let Some(color) = self.subtitles_background.as_ref().filter(|c| !c.is_valid()) {
+            return Err(SettingsError::SubtitlesBackground(color.0.to_owned()));
+        }
+        if !self.subtitles_color.is_valid() {
+            return Err(SettingsError::SubtitlesColor(
+                self.subtitles_color.0.to_owned(),
+            ));
+        }
+        if !self.subtitles_outline_color.is_valid() {
+            return Err(SettingsError::SubtitlesOutlineColor(
+                self.subtitles_outline_color.0.to_owned(),
+            ));
+        }
+        if !["http", "https"].contains(&self.server_url.scheme()) {
+            return Err(SettingsError::ServerUrl(self.server_url.to_string()));
+        }
+        Ok(())
     }
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
-            language: "eng".to_string(),
-            subtitles_size: "100%".to_string(),
-            subtitles_language: "eng".to_string(),
-            subtitles_background: "".to_string(),
-            subtitles_color: "#fff".to_string(),
-            subtitles_outline_color: "#000".to_string(),
-            autoplay_next_vid: "false".to_string(),
-            server_url: "http://127.0.0.1:11470/".to_string(),
-            use_external_player: "false".to_string(),
-            pause_on_lost_focus: "false".to_string(),
-            show_vid_overview: "false".to_string(),
+            language: Language::parse("eng"),
+            subtitles_size: Percentage(100),
+            subtitles_language: Language::parse("eng"),
+            subtitles_background: None,
+            subtitles_color: Color("#fff".to_string()),
+            subtitles_outline_color: Color("#000".to_string()),
+            autoplay_next_vid: false,
+            server_url: Url::parse("http://127.0.0.1:11470/").expect("default server URL"),
+            use_external_player: false,
+            pause_on_lost_focus: false,
+            show_vid_overview: false,
+            stream_ranking: StreamRankingPrefs::default(),
+        }
+    }
+}
+
+// The stored settings are loaded field by field: fields which are invalid (e.g. a server_url
+// which is not http(s)), or which can't be deserialized, fall back to their defaults
+pub fn deserialize_stored_settings<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Settings, D::Error> {
+    let stored = match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Object(stored) => stored,
+        _ => return Ok(Settings::default()),
+    };
+    let mut settings = Settings::default();
+    for (key, value) in stored {
+        let mut merged = serde_json::to_value(&settings).expect("settings must serialize");
+        merged[key.as_str()] = value;
+        match serde_json::from_value::<Settings>(merged) {
+            Ok(merged) if merged.validate().is_ok() => settings = merged,
+            _ => (),
         }
     }
+    Ok(settings)
+}
+
+// Upgrades the settings JSON from before they were typed, where everything apart from
+// the languages was stored as a string; values which can't be migrated are dropped
+pub fn migrate_settings(settings: &mut serde_json::Value) {
+    let settings = match settings.as_object_mut() {
+        Some(settings) => settings,
+        None => return,
+    };
+    for key in &[
+        "autoplay_next_vid",
+        "use_external_player",
+        "pause_on_lost_focus",
+        "show_vid_overview",
+    ] {
+        let migrated = match settings.get(*key) {
+            Some(serde_json::Value::String(s)) => s.parse::<bool>().ok().map(Into::into),
+            Some(value) => Some(value.to_owned()),
+            None => None,
+        };
+        migrate_value(settings, key, migrated);
+    }
+    let size = match settings.get("subtitles_size") {
+        Some(serde_json::Value::String(s)) => s
+            .trim_end_matches('%')
+            .trim()
+            .parse::<u16>()
+            .ok()
+            .map(Into::into),
+        Some(value) => Some(value.to_owned()),
+        None => None,
+    };
+    migrate_value(settings, "subtitles_size", size);
+    if settings.get("subtitles_background") == Some(&"".into()) {
+        settings.insert("subtitles_background".to_owned(), serde_json::Value::Null);
+    }
+}
+
+fn migrate_value(
+    settings: &mut serde_json::Map<String, serde_json::Value>,
+    key: &str,
+    value: Option<serde_json::Value>,
+) {
+    match value {
+        Some(value) => settings.insert(key.to_owned(), value),
+        None => settings.remove(key),
+    };
+}
+
+mod url_string {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use url::Url;
+
+    pub fn serialize<S: Serializer>(url: &Url, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(url.as_str())
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Url, D::Error> {
+        let url = String::deserialize(deserializer)?;
+        Url::parse(&url).map_err(D::Error::custom)
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
@@ -313,3 +492,31 @@ impl<Env: Environment + 'static> UpdateWithCtx<Ctx<Env>> for StreamingServerSett
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoint_keeps_the_path() {
+        let endpoint = |server_url: &str| {
+            Settings {
+                server_url: Url::parse(server_url).unwrap(),
+                ..Settings::default()
+            }
+            .get_endpoint()
+        };
+        assert_eq!(
+            endpoint("http://127.0.0.1:11470/"),
+            "http://127.0.0.1:11470/settings"
+        );
+        assert_eq!(
+            endpoint("http://host:11470/base"),
+            "http://host:11470/base/settings"
+        );
+        assert_eq!(
+            endpoint("http://host:11470/base/"),
+            "http://host:11470/base/settings"
+        );
+    }
+}
@@ -1,28 +1,103 @@
 use super::addons::*;
 use crate::state_types::*;
 use crate::types::addons::{AggrRequest, ResourceRef};
-use crate::types::Stream;
+use crate::types::{Stream, StreamQuality};
 use serde_derive::*;
+use std::cmp::Reverse;
+use std::collections::HashMap;
 
-// @TODO this will become Detail
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedStream {
+    pub stream: Stream,
+    pub quality: StreamQuality,
+    // The transport URLs of all the add-ons which returned this stream
+    pub addons: Vec<String>,
+}
 
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct Streams {
     pub groups: Vec<ItemsGroup<Vec<Stream>>>,
+    // The streams of all ready groups: deduplicated, and best first
+    pub ranked: Vec<RankedStream>,
+    // Reset on every load, which cancels the effects of the previous one
+    #[serde(skip)]
+    load_effects: EffectsHandle,
 }
 impl<Env: Environment + 'static> UpdateWithCtx<Ctx<Env>> for Streams {
     fn update(&mut self, ctx: &Ctx<Env>, msg: &Msg) -> Effects {
-        match msg {
+        let fx = match msg {
             Msg::Action(Action::Load(ActionLoad::Streams { type_name, id })) => {
                 let resource_ref = ResourceRef::without_extra("stream", type_name, id);
-                let (groups, effects) = addon_aggr_new::<Env, _>(
+                let (groups, effects) = addon_aggr_new_cached::<Env, _>(
                     &ctx.content.addons,
+                    &ctx.addon_cache,
                     &AggrRequest::AllOfResource(resource_ref),
                 );
                 self.groups = groups;
-                effects
+                self.load_effects.reset();
+                effects.cancellable(&self.load_effects)
             }
+            // The ranking preferences may have changed
+            Msg::Action(Action::Settings(ActionSettings::Store(_))) => Effects::none(),
             _ => addon_aggr_update(&mut self.groups, msg),
+        };
+        if fx.has_changed {
+            self.ranked = rank_streams(&self.groups, &ctx.content.settings.stream_ranking);
+        }
+        fx
+    }
+}
+
+fn rank_streams(
+    groups: &[ItemsGroup<Vec<Stream>>],
+    prefs: &StreamRankingPrefs,
+) -> Vec<RankedStream> {
+    let mut ranked: Vec<RankedStream> = vec![];
+    // The index in ranked of each dedup_key
+    let mut indexes: HashMap<String, usize> = HashMap::new();
+    let ready_streams = groups.iter().flat_map(|group| match &group.content {
+        Loadable::Ready(streams) => streams
+            .iter()
+            .map(|stream| (&group.addon_req().base, stream))
+            .collect(),
+        _ => vec![],
+    });
+    for (transport_url, stream) in ready_streams {
+        let key = stream.dedup_key();
+        match indexes.get(&key).copied() {
+            Some(idx) => {
+                let existing = &mut ranked[idx];
+                if !existing.addons.contains(transport_url) {
+                    existing.addons.push(transport_url.to_owned());
+                }
+                if existing.stream.title.is_none() {
+                    existing.stream.title = stream.title.to_owned();
+                }
+                existing.quality = existing.quality.max(stream.quality());
+            }
+            None => {
+                indexes.insert(key, ranked.len());
+                ranked.push(RankedStream {
+                    stream: stream.to_owned(),
+                    quality: stream.quality(),
+                    addons: vec![transport_url.to_owned()],
+                });
+            }
         }
     }
+    // The sort is stable, so equally ranked streams stay in the order of the add-ons
+    ranked.sort_by_key(|r| {
+        let quality = match prefs.max_quality {
+            Some(max_quality) => r.quality.min(max_quality),
+            None => r.quality,
+        };
+        Reverse((
+            prefs.prefer_web_ready && r.stream.is_web_ready(),
+            r.stream.is_p2p() == prefs.prefer_p2p,
+            quality,
+            r.addons.len(),
+        ))
+    });
+    ranked
 }
@@ -0,0 +1,118 @@
+use super::addons::*;
+use crate::state_types::*;
+use crate::types::addons::{AggrRequest, ExtraProp, ResourceRef};
+use crate::types::{Language, Stream, SubtitlesSource};
+use serde_derive::*;
+
+// The names of the extra properties
+const VIDEO_HASH: &str = "videoHash";
+const VIDEO_SIZE: &str = "videoSize";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitlesSelected {
+    pub type_name: String,
+    pub id: String,
+    pub video_hash: Option<String>,
+    pub video_size: Option<u64>,
+    pub stream: Stream,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SubtitlesLanguage {
+    pub lang: Language,
+    pub subtitles: Vec<SubtitlesSource>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Subtitles {
+    pub selected: Option<SubtitlesSelected>,
+    pub groups: Vec<ItemsGroup<Vec<SubtitlesSource>>>,
+    // The subtitles of the stream itself and of all ready groups, grouped by language;
+    // the preferred language (Settings::subtitles_language) is always first
+    pub languages: Vec<SubtitlesLanguage>,
+    // Reset on every load, which cancels the effects of the previous one
+    #[serde(skip)]
+    load_effects: EffectsHandle,
+}
+impl<Env: Environment + 'static> UpdateWithCtx<Ctx<Env>> for Subtitles {
+    fn update(&mut self, ctx: &Ctx<Env>, msg: &Msg) -> Effects {
+        let fx = match msg {
+            Msg::Action(Action::Load(ActionLoad::Subtitles {
+                type_name,
+                id,
+                video_hash,
+                video_size,
+                stream,
+            })) => {
+                // Those help subtitles add-ons to find subtitles matching the exact video file
+                let mut extra: Vec<ExtraProp> = vec![];
+                if let Some(video_hash) = video_hash {
+                    extra.push((VIDEO_HASH.to_owned(), video_hash.to_owned()));
+                }
+                if let Some(video_size) = video_size {
+                    extra.push((VIDEO_SIZE.to_owned(), video_size.to_string()));
+                }
+                let resource_ref = ResourceRef::with_extra("subtitles", type_name, id, &extra);
+                let (groups, effects) = addon_aggr_new::<Env, _>(
+                    &ctx.content.addons,
+                    &AggrRequest::AllOfResource(resource_ref),
+                );
+                self.load_effects.cancel();
+                *self = Subtitles {
+                    selected: Some(SubtitlesSelected {
+                        type_name: type_name.to_owned(),
+                        id: id.to_owned(),
+                        video_hash: video_hash.to_owned(),
+                        video_size: video_size.to_owned(),
+                        stream: *stream.to_owned(),
+                    }),
+                    groups,
+                    ..Default::default()
+                };
+                effects.cancellable(&self.load_effects)
+            }
+            // The preferred language may have changed
+            Msg::Action(Action::Settings(ActionSettings::Store(_))) => Effects::none(),
+            _ => addon_aggr_update(&mut self.groups, msg),
+        };
+        if fx.has_changed {
+            self.languages = group_by_language(
+                self.selected.as_ref().map(|s| &s.stream),
+                &self.groups,
+                &ctx.content.settings.subtitles_language,
+            );
+        }
+        fx
+    }
+}
+
+fn group_by_language(
+    stream: Option<&Stream>,
+    groups: &[ItemsGroup<Vec<SubtitlesSource>>],
+    preferred_lang: &Language,
+) -> Vec<SubtitlesLanguage> {
+    // The subtitles of the stream itself come first, since they're most likely to be in sync
+    let embedded = stream.iter().flat_map(|stream| stream.subtitles.iter());
+    let from_addons = groups.iter().flat_map(|group| match &group.content {
+        Loadable::Ready(subtitles) => subtitles.iter().collect(),
+        _ => vec![],
+    });
+    let mut languages: Vec<SubtitlesLanguage> = vec![];
+    for source in embedded.chain(from_addons) {
+        match languages.iter_mut().find(|l| l.lang == source.lang) {
+            Some(language) => {
+                if language.subtitles.iter().all(|s| s.url != source.url) {
+                    language.subtitles.push(source.to_owned());
+                }
+            }
+            None => languages.push(SubtitlesLanguage {
+                lang: source.lang.to_owned(),
+                subtitles: vec![source.to_owned()],
+            }),
+        }
+    }
+    // The sort is stable, so the rest remain in the order they were found in
+    languages.sort_by_key(|l| &l.lang != preferred_lang);
+    languages
+}
@@ -1,7 +1,8 @@
-use crate::state_types::{Settings, StreamingServerSettings};
+use super::player::*;
+use crate::state_types::{LibraryRequest, Settings, StreamingServerSettings};
 use crate::types::addons::*;
 use crate::types::api::GDPRConsent;
-use crate::types::LibItem;
+use crate::types::{LibItem, MetaDetail, Stream};
 use serde_derive::*;
 
 //
@@ -14,6 +15,10 @@ pub enum ActionLoad {
         extra: Vec<ExtraProp>,
     },
     CatalogFiltered(ResourceRequest),
+    AddonsDiscovery {
+        request: ResourceRequest,
+        type_filter: Option<String>,
+    },
     Detail {
         type_name: String,
         id: String,
@@ -24,13 +29,66 @@ pub enum ActionLoad {
         id: String,
     },
     Notifications,
+    LibraryFiltered(LibraryRequest),
+    Player {
+        type_name: String,
+        id: String,
+        video_id: Option<String>,
+        stream: Box<Stream>,
+    },
+    Subtitles {
+        type_name: String,
+        // The id of the video
+        id: String,
+        video_hash: Option<String>,
+        video_size: Option<u64>,
+        stream: Box<Stream>,
+    },
 }
 
 #[derive(Debug, Deserialize, Clone)]
 #[serde(tag = "addonOp", content = "args")]
 pub enum ActionAddon {
-    Remove { transport_url: TransportUrl },
+    Remove {
+        transport_url: TransportUrl,
+    },
     Install(Box<Descriptor>),
+    // The order of the add-ons is the order of the catalogs and the streams
+    MoveUp {
+        transport_url: TransportUrl,
+    },
+    MoveDown {
+        transport_url: TransportUrl,
+    },
+    Enable {
+        transport_url: TransportUrl,
+    },
+    Disable {
+        transport_url: TransportUrl,
+    },
+    // Catalogs are shown on the board (see CatalogGrouped) unless hidden
+    HideCatalog {
+        transport_url: TransportUrl,
+        type_name: String,
+        id: String,
+    },
+    ShowCatalog {
+        transport_url: TransportUrl,
+        type_name: String,
+        id: String,
+    },
+    // The position is among all catalogs on the board, including the hidden ones
+    MoveCatalog {
+        transport_url: TransportUrl,
+        type_name: String,
+        id: String,
+        position: usize,
+    },
+    // Fetches the manifest first; see Ctx for how it's installed
+    InstallFromUrl(TransportUrl),
+    // Refetches the manifests of all installed add-ons, and upgrades the ones with newer versions
+    // The UI is expected to do this every once in a while, e.g. on start
+    RefreshManifests,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -60,6 +118,23 @@ pub enum ActionUser {
     PushAddons,
     LibSync,
     LibUpdate(LibItem),
+    // Those build the LibItem from what's in the library, and send it like LibUpdate does
+    AddToLibrary(Box<MetaDetail>),
+    RemoveFromLibrary(String),
+    RewindLibItem(String),
+    MarkAsWatched(String, bool),
+    ToggleNotifications(String),
+    // The videos of the meta tell which bit of LibItemState::watched is which
+    MarkVideoAsWatched {
+        meta: Box<MetaDetail>,
+        video_id: String,
+        is_watched: bool,
+    },
+    MarkSeasonAsWatched {
+        meta: Box<MetaDetail>,
+        season: u32,
+        is_watched: bool,
+    },
     // @TODO consider PullUser, PushUser?
 }
 
@@ -68,7 +143,12 @@ pub enum ActionUser {
 pub enum Action {
     LoadCtx,
     Load(ActionLoad),
+    // Leaving the current route, e.g. closing the player
+    Unload,
     Settings(ActionSettings),
     AddonOp(ActionAddon),
     UserOp(ActionUser),
+    PlayerOp(PlayerAction),
+    // Reported by the player implementation itself
+    PlayerEvent(PlayerEvent),
 }
@@ -1,14 +1,18 @@
-use crate::state_types::{CtxContent, EnvError, SsSettings};
+use crate::state_types::{AddonCache, CtxContent, EnvError, SettingsError, SsSettings};
 use crate::types::addons::*;
 use crate::types::api::*;
-use crate::types::{LibBucket, Stream};
+use crate::types::LibBucket;
 use derive_more::*;
+use semver::Version;
 use serde_derive::*;
 use std::error::Error;
 
 mod actions;
 pub use actions::*;
 
+mod player;
+pub use player::*;
+
 //
 // Intermediery messages
 // those are emitted by the middlewares and received by containers
@@ -28,8 +32,34 @@ pub enum Internal {
     // Library: pulled some newer items from the API
     // this will extend the current index of libitems, it doesn't replace it
     LibSyncPulled(LibBucket),
+    // Library items which could not be pushed; they go to the outbox of LibSync
+    LibPushFailed(LibBucket),
+    LibOutboxLoaded(LibBucket),
+    // The outbox was pushed: the items are as they were when pushing
+    LibOutboxPushed(LibBucket),
+    LibOutboxPushFailed,
+    LibOutboxPersisted,
+    // Timers of LibSync: retrying to push the outbox, and syncing periodically
+    LibOutboxRetry,
+    LibSyncScheduled,
     // Response from an add-on
     AddonResponse(ResourceRequest, Box<Result<ResourceResponse, EnvError>>),
+    // Successful response from an add-on that should be cached; the AddonCache turns this
+    // into an AddonResponse, and persists it a bit later
+    AddonCacheableResponse(ResourceRequest, Box<(ResourceResponse, CacheHints)>),
+    AddonCacheLoaded(AddonCache),
+    AddonCachePersist,
+    // The delay before persisting failed
+    AddonCachePersistCancelled,
+    AddonCachePersisted,
+    // The manifest of an add-on which is being installed by its URL
+    AddonManifestFetched(TransportUrl, Box<Manifest>),
+    // The current manifests of the installed add-ons, see ActionAddon::RefreshManifests
+    AddonManifestsRefreshed(Vec<(TransportUrl, Result<Manifest, EnvError>)>),
+    // Timer of the Ctx: refreshing the manifests periodically
+    AddonsRefreshScheduled,
+    // An effect that was no longer needed, see EffectsHandle
+    EffectCancelled,
     StreamingServerSettingsLoaded(SsSettings),
     StreamingServerSettingsErrored(String),
 }
@@ -55,6 +85,18 @@ impl From<Box<dyn Error>> for CtxError {
     }
 }
 
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "err", content = "args")]
+pub enum AddonManifestError {
+    ManifestFetch(String),
+    // Only the fatal issues, see ManifestIssue::is_fatal
+    ManifestInvalid(Vec<ManifestIssue>),
+    // An add-on with the same id is installed from another URL, and it's protected
+    ReplacesProtected,
+    // The installed version, which is newer
+    OlderVersion(Version),
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(tag = "event", content = "args")]
 pub enum Event {
@@ -70,6 +112,15 @@ pub enum Event {
     LibPushed,
     LibFatal(CtxError),
     SettingsStoreError(String),
+    AddonInstalled(TransportUrl),
+    // The add-on replaced an older version of itself: from the version, to the version
+    AddonUpgraded(TransportUrl, Version, Version),
+    AddonInstallFailed(TransportUrl, AddonManifestError),
+    // The refreshed manifest is not newer than the installed one
+    AddonUpToDate(TransportUrl),
+    AddonRefreshFailed(TransportUrl, AddonManifestError),
+    // The settings were not stored, since they are invalid
+    SettingsInvalid(SettingsError),
 }
 
 //
@@ -82,25 +133,3 @@ pub enum Msg {
     Internal(Internal),
     Event(Event),
 }
-
-// @TODO separate module
-pub enum PlayerProp {
-    Time(u64),
-    Volume(u8),
-    Paused(bool),
-}
-pub enum PlayerAction {
-    GetAllProps,
-    SetProp(PlayerProp),
-    // by default, all are observed
-    //ObserveProp()
-    // @TODO should this be PlayerCommand
-    Load(Stream),
-}
-
-pub enum PlayerEvent {
-    PropChanged(PlayerProp),
-    PropValue(PlayerProp),
-    Loaded, // @TODO: tracks and etc.
-    Error,  // @TODO: error type
-}
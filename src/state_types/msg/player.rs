@@ -0,0 +1,34 @@
+use serde_derive::*;
+
+// Player messages
+// the frontend is responsible for the actual playback: it executes the PlayerAction-s
+// and reports back what happened through PlayerEvent-s
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(tag = "prop", content = "value")]
+pub enum PlayerProp {
+    // All times are in milliseconds
+    Time(u64),
+    Duration(u64),
+    Volume(u8),
+    Paused(bool),
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(tag = "playerOp", content = "args")]
+pub enum PlayerAction {
+    GetAllProps,
+    SetProp(PlayerProp),
+    // by default, all are observed
+    //ObserveProp()
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(tag = "playerEvent", content = "args")]
+pub enum PlayerEvent {
+    PropChanged(PlayerProp),
+    PropValue(PlayerProp),
+    Loaded, // @TODO: tracks and etc.
+    Error(String),
+    // The video was played until the end
+    Ended,
+}
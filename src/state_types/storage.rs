@@ -0,0 +1,131 @@
+use crate::state_types::{migrate_settings, EnvFuture, Environment};
+use futures::{future, Future};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub const USER_DATA_SLOT: &str = "userData";
+pub const LIBRARY_SLOT: &str = "library";
+pub const LIBRARY_RECENT_SLOT: &str = "recent_library";
+pub const LIBRARY_OUTBOX_SLOT: &str = "library_outbox";
+const VERSION_SLOT: &str = "schema_version";
+// The slots which are migrated together; the addon cache is not here, since it can always be dropped
+const SLOTS: &[&str] = &[
+    USER_DATA_SLOT,
+    LIBRARY_SLOT,
+    LIBRARY_RECENT_SLOT,
+    LIBRARY_OUTBOX_SLOT,
+];
+
+// The stored slots, by name; slots which are not stored are missing
+pub type StorageSlots = HashMap<&'static str, Value>;
+type Migration = fn(&mut StorageSlots) -> Result<(), String>;
+
+// MIGRATIONS[n] upgrades the storage from version n to n + 1
+// Adding a field with a default doesn't need a migration, anything else that changes the stored JSON does
+const MIGRATIONS: &[Migration] = &[typed_settings];
+pub const STORAGE_VERSION: usize = MIGRATIONS.len();
+
+// Runs the migrations the storage needs, if any; this must be done before reading any of the SLOTS
+// If a migration fails, the slots are backed up (see backup_slot) and we start over
+pub fn migrate_storage<Env: Environment + 'static>() -> EnvFuture<()> {
+    let ft = Env::get_storage::<usize>(VERSION_SLOT).and_then(|version| -> EnvFuture<()> {
+        // Storage from before the versioning has no version at all
+        let version = version.unwrap_or(0);
+        if version >= STORAGE_VERSION {
+            return Box::new(future::ok(()));
+        }
+        let reads = SLOTS
+            .iter()
+            .map(|&slot| Env::get_storage::<Value>(slot).map(move |value| (slot, value)));
+        let ft = future::join_all(reads).and_then(move |values| {
+            let stored: StorageSlots = values
+                .into_iter()
+                .filter_map(|(slot, value)| Some((slot, value?)))
+                .collect();
+            let mut migrated = stored.to_owned();
+            let writes = match MIGRATIONS[version..]
+                .iter()
+                .try_for_each(|migration| migration(&mut migrated))
+            {
+                Ok(()) => migrated
+                    .iter()
+                    .map(|(slot, value)| Env::set_storage(slot, Some(value)))
+                    .collect(),
+                Err(_) => stored
+                    .iter()
+                    .map(|(slot, value)| backup_slot::<Env, Value>(slot, value, None))
+                    .collect::<Vec<_>>(),
+            };
+            future::join_all(writes)
+                .and_then(|_| Env::set_storage(VERSION_SLOT, Some(&STORAGE_VERSION)))
+        });
+        Box::new(ft)
+    });
+    Box::new(ft)
+}
+
+// Reads a slot; if it can't be deserialized (e.g. it was changed without a migration),
+// it's backed up and treated as if nothing was stored
+pub fn get_storage_or_backup<Env, T>(slot: &'static str) -> EnvFuture<Option<T>>
+where
+    Env: Environment + 'static,
+    T: Serialize + DeserializeOwned + 'static,
+{
+    get_storage_or_recover::<Env, T>(slot, |_| None)
+}
+
+// Like get_storage_or_backup, but the slot is replaced by whatever `recover` can salvage
+// from the value which can't be deserialized
+pub fn get_storage_or_recover<Env, T>(
+    slot: &'static str,
+    recover: fn(&Value) -> Option<T>,
+) -> EnvFuture<Option<T>>
+where
+    Env: Environment + 'static,
+    T: Serialize + DeserializeOwned + 'static,
+{
+    let ft = Env::get_storage::<Value>(slot).and_then(move |value| -> EnvFuture<Option<T>> {
+        match value.map(|value| (serde_json::from_value(value.to_owned()), value)) {
+            Some((Ok(loaded), _)) => Box::new(future::ok(Some(loaded))),
+            Some((Err(_), value)) => {
+                let recovered = recover(&value);
+                let ft =
+                    backup_slot::<Env, T>(slot, &value, recovered.as_ref()).map(move |_| recovered);
+                Box::new(ft)
+            }
+            None => Box::new(future::ok(None)),
+        }
+    });
+    Box::new(ft)
+}
+
+// Moves the value to <slot>_backup, so that it can be recovered manually
+fn backup_slot<Env, T>(slot: &str, value: &Value, replacement: Option<&T>) -> EnvFuture<()>
+where
+    Env: Environment + 'static,
+    T: Serialize,
+{
+    let ft = Env::set_storage(&format!("{}_backup", slot), Some(value))
+        .join(Env::set_storage(slot, replacement))
+        .map(|_| ());
+    Box::new(ft)
+}
+
+//
+// Migrations
+//
+// 0 -> 1: the settings are typed, instead of being strings
+fn typed_settings(slots: &mut StorageSlots) -> Result<(), String> {
+    match slots.get_mut(USER_DATA_SLOT) {
+        Some(Value::Object(user_data)) => {
+            if let Some(settings) = user_data.get_mut("settings") {
+                migrate_settings(settings);
+            }
+            Ok(())
+        }
+        Some(_) => Err("userData is not an object".to_owned()),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,161 @@
+use std::error::Error;
+use std::fmt;
+
+mod tracks;
+pub use self::tracks::*;
+
+mod srt;
+pub use self::srt::*;
+
+mod webvtt;
+pub use self::webvtt::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubtitlesError {
+    MissingHeader,
+    // The line (starting from 1) of a timing that can't be parsed
+    InvalidTiming(usize),
+}
+impl fmt::Display for SubtitlesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SubtitlesError::MissingHeader => write!(f, "missing WEBVTT header"),
+            SubtitlesError::InvalidTiming(line) => write!(f, "invalid timing on line {}", line),
+        }
+    }
+}
+impl Error for SubtitlesError {}
+
+// The format is detected by the WebVTT header, anything else is parsed as SRT
+pub fn parse_subtitles(text: &str) -> Result<Vec<Track>, SubtitlesError> {
+    if text.trim_start_matches('\u{feff}').starts_with("WEBVTT") {
+        parse_webvtt(text)
+    } else {
+        parse_srt(text)
+    }
+}
+
+// A block is a group of lines separated by blank lines; each line comes with its number
+type Block<'a> = Vec<(usize, &'a str)>;
+
+fn blocks(text: &str) -> Vec<Block<'_>> {
+    let mut blocks = vec![];
+    let mut current: Block = vec![];
+    let lines = text.trim_start_matches('\u{feff}').lines().enumerate();
+    for (idx, line) in lines {
+        // .lines() already takes care of \r\n, but not of trailing whitespace
+        let line = line.trim_end();
+        if line.is_empty() {
+            if !current.is_empty() {
+                blocks.push(current);
+                current = vec![];
+            }
+        } else {
+            current.push((idx + 1, line));
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+    blocks
+}
+
+// The timing line is the first one which has an arrow; the lines after it are the content
+// Cues which end before they start are skipped, since they would never be shown
+fn parse_cue(block: &[(usize, &str)]) -> Result<Option<Track>, SubtitlesError> {
+    let timing_idx = match block.iter().position(|(_, line)| line.contains("-->")) {
+        Some(idx) => idx,
+        None => return Ok(None),
+    };
+    let (line_no, timing) = block[timing_idx];
+    let (start, end) = parse_timing(timing).ok_or(SubtitlesError::InvalidTiming(line_no))?;
+    if end < start {
+        return Ok(None);
+    }
+    let content = block[timing_idx + 1..]
+        .iter()
+        .map(|(_, line)| *line)
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(Some(Track {
+        start,
+        end,
+        content,
+    }))
+}
+
+// "00:00:01,000 --> 00:00:04,000", where anything after the end time (e.g. WebVTT cue settings)
+// is ignored
+fn parse_timing(line: &str) -> Option<(u64, u64)> {
+    let mut parts = line.split("-->");
+    let start = parse_timestamp(parts.next()?.trim())?;
+    let end = parse_timestamp(parts.next()?.split_whitespace().next()?)?;
+    Some((start, end))
+}
+
+// "01:02:03,456" (SRT), "01:02:03.456" or "02:03.456" (WebVTT, where hours are optional)
+fn parse_timestamp(s: &str) -> Option<u64> {
+    let mut parts = s.rsplitn(2, &[',', '.'][..]);
+    let (hms, millis) = match (parts.next()?, parts.next()) {
+        (millis, Some(hms)) => (hms, parse_millis(millis)?),
+        (hms, None) => (hms, 0),
+    };
+    let hms = hms
+        .split(':')
+        .map(|x| x.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let seconds = match hms.as_slice() {
+        [h, m, s] => h * 3600 + m * 60 + s,
+        [m, s] => m * 60 + s,
+        _ => return None,
+    };
+    Some(seconds * 1000 + millis)
+}
+
+// The fraction of the second, so "5" is 500ms
+fn parse_millis(s: &str) -> Option<u64> {
+    if s.is_empty() || s.len() > 3 || !s.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    format!("{:0<3}", s).parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_timestamp("01:02:03,456"), Some(3_723_456));
+        assert_eq!(parse_timestamp("01:02:03.456"), Some(3_723_456));
+        assert_eq!(parse_timestamp("02:03.456"), Some(123_456));
+        assert_eq!(parse_timestamp("00:00:01,5"), Some(1_500));
+        assert_eq!(parse_timestamp("00:00:01"), Some(1_000));
+        assert_eq!(parse_timestamp("1:2:3:4.000"), None);
+        assert_eq!(parse_timestamp("aa:00.000"), None);
+        assert_eq!(
+            parse_timing("00:01.000 --> 00:04.000 align:start position:10%"),
+            Some((1_000, 4_000))
+        );
+    }
+
+    #[test]
+    fn detects_format() {
+        let vtt = "WEBVTT\n\n00:01.000 --> 00:02.000\nHello\n";
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nHello\n";
+        assert_eq!(parse_subtitles(vtt), parse_subtitles(srt));
+        assert_eq!(parse_subtitles(srt).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn skips_cues_ending_before_they_start() {
+        let srt = "1\n00:00:05,000 --> 00:00:02,000\nBackwards\n\n2\n00:00:06,000 --> 00:00:07,000\nHello\n";
+        let vtt =
+            "WEBVTT\n\n00:05.000 --> 00:02.000\nBackwards\n\n00:06.000 --> 00:07.000\nHello\n";
+        for text in &[srt, vtt] {
+            let tracks = parse_subtitles(text).unwrap();
+            assert_eq!(tracks.len(), 1);
+            assert_eq!(tracks[0].content, "Hello");
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use super::{blocks, parse_cue, SubtitlesError, Track};
+
+// Blocks without a timing are skipped, since they're quite common in the wild (e.g. ads)
+pub fn parse_srt(text: &str) -> Result<Vec<Track>, SubtitlesError> {
+    let mut tracks = vec![];
+    for block in blocks(text) {
+        if let Some(track) = parse_cue(&block)? {
+            tracks.push(track);
+        }
+    }
+    Ok(tracks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_srt() {
+        let text = "\u{feff}1\r\n00:00:01,000 --> 00:00:04,000\r\nHello\r\n<i>world</i>\r\n\r\n2\r\n00:00:05,500 --> 00:00:07,000\r\nBye\r\n\r\n\r\n";
+        assert_eq!(
+            parse_srt(text),
+            Ok(vec![
+                Track {
+                    start: 1_000,
+                    end: 4_000,
+                    content: "Hello\n<i>world</i>".to_owned(),
+                },
+                Track {
+                    start: 5_500,
+                    end: 7_000,
+                    content: "Bye".to_owned(),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn invalid_timing() {
+        let text =
+            "1\n00:00:01,000 --> 00:00:04,000\nHello\n\n2\n00:00:xx,000 --> 00:00:07,000\nBye\n";
+        assert_eq!(parse_srt(text), Err(SubtitlesError::InvalidTiming(6)));
+    }
+}
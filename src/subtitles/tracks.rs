@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+use std::ops::Bound::{Included, Unbounded};
+
+// All times are in milliseconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub start: u64,
+    pub end: u64,
+    // @TODO: should this be generic?
+    pub content: String,
+}
+
+// Shifts the tracks permanently, e.g. when the subtitles were made for a different release;
+// tracks which would start before 0 are clamped to it
+pub fn offset_tracks(tracks: &mut [Track], offset: i64) {
+    for track in tracks.iter_mut() {
+        track.start = offset_time(track.start, offset);
+        track.end = offset_time(track.end, offset);
+    }
+}
+
+fn offset_time(time: u64, offset: i64) -> u64 {
+    if offset < 0 {
+        time.saturating_sub(offset.unsigned_abs())
+    } else {
+        time.saturating_add(offset as u64)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finder {
+    start_map: BTreeMap<u64, Vec<usize>>,
+    pub tracks: Vec<Track>,
+    // Positive values show the subtitles later, negative ones earlier;
+    // unlike offset_tracks, this is meant to be adjusted by the user during playback
+    pub delay: i64,
+}
+enum PointType {
+    Start,
+    End,
+}
+struct Point {
+    at: u64,
+    t: PointType,
+    idx: usize,
+}
+impl Finder {
+    pub fn from_tracks(tracks: Vec<Track>) -> Finder {
+        // The other option is to get all the points, and then for each track, iterate from
+        // start_map at start, through all points < end, and push the track idx into their Vec
+        let mut points = Vec::with_capacity(tracks.len() * 2);
+        for (idx, track) in tracks.iter().enumerate() {
+            points.push(Point {
+                at: track.start,
+                t: PointType::Start,
+                idx,
+            });
+            points.push(Point {
+                at: track.end,
+                t: PointType::End,
+                idx,
+            });
+        }
+        points.sort_by_key(|p| p.at);
+        let mut track_idxs: Vec<usize> = vec![];
+        let mut start_map = BTreeMap::new();
+        for p in &points {
+            match &p.t {
+                PointType::Start => track_idxs.push(p.idx),
+                // @TODO remove might be expensive
+                PointType::End => track_idxs.retain(|&x| x != p.idx),
+            }
+            // overriding the value in the map should be fine, as this is the latest state
+            start_map.insert(p.at, track_idxs.clone());
+        }
+        Finder {
+            start_map,
+            tracks,
+            delay: 0,
+        }
+    }
+    pub fn set_delay(&mut self, delay: i64) {
+        self.delay = delay;
+    }
+    // The indexes of the tracks which should be shown at the given time (of the video)
+    pub fn find(&self, x: u64) -> Option<&[usize]> {
+        self.start_map
+            .range((Unbounded, Included(offset_time(x, -self.delay))))
+            .last()
+            .map(|(_, idxs)| idxs.as_slice())
+    }
+    pub fn find_tracks(&self, x: u64) -> Vec<&Track> {
+        self.find(x)
+            .unwrap_or_default()
+            .iter()
+            .map(|&idx| &self.tracks[idx])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(start: u64, end: u64) -> Track {
+        Track {
+            start,
+            end,
+            content: "".to_string(),
+        }
+    }
+
+    #[test]
+    fn finds_in_range() {
+        let finder = Finder::from_tracks(vec![
+            track(0, 16),
+            track(2, 4),
+            track(4, 8),
+            track(8, 10),
+            track(9, 11),
+            track(10, 12),
+            track(13, 15),
+        ]);
+        assert_eq!(finder.find(9), Some(vec![0, 3, 4].as_slice()));
+        assert_eq!(finder.find(5), Some(vec![0, 2].as_slice()));
+        assert_eq!(finder.find(16), Some(vec![].as_slice()));
+    }
+
+    #[test]
+    fn delay_and_offset() {
+        let mut finder = Finder::from_tracks(vec![track(1000, 2000), track(3000, 4000)]);
+        assert_eq!(finder.find(500), None);
+        finder.set_delay(1000);
+        assert_eq!(finder.find(1500), None);
+        assert_eq!(finder.find(2500), Some(vec![0].as_slice()));
+        finder.set_delay(-1000);
+        assert_eq!(finder.find(2500), Some(vec![1].as_slice()));
+
+        let mut tracks = vec![track(1000, 2000), track(3000, 4000)];
+        offset_tracks(&mut tracks, -1500);
+        assert_eq!(tracks, vec![track(0, 500), track(1500, 2500)]);
+    }
+}
@@ -0,0 +1,54 @@
+use super::{blocks, parse_cue, SubtitlesError, Track};
+
+pub fn parse_webvtt(text: &str) -> Result<Vec<Track>, SubtitlesError> {
+    let blocks = blocks(text);
+    match blocks.first() {
+        Some(header) if header[0].1.starts_with("WEBVTT") => (),
+        _ => return Err(SubtitlesError::MissingHeader),
+    };
+    let mut tracks = vec![];
+    // The first block is the header
+    for block in blocks.iter().skip(1) {
+        let first_line = block[0].1;
+        let is_cue = !["NOTE", "STYLE", "REGION"]
+            .iter()
+            .any(|kind| first_line.starts_with(kind));
+        if is_cue {
+            if let Some(track) = parse_cue(block)? {
+                tracks.push(track);
+            }
+        }
+    }
+    Ok(tracks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_webvtt() {
+        let text = "WEBVTT - Some title\nKind: captions\n\nNOTE this is a comment\n00:00.000 --> 00:01.000\n\nSTYLE\n::cue { color: red }\n\nintro\n00:01.000 --> 00:04.000 align:start\n<v Roger>Hello</v>\n\n01:00:05.500 --> 01:00:07.000\nBye\n";
+        assert_eq!(
+            parse_webvtt(text),
+            Ok(vec![
+                Track {
+                    start: 1_000,
+                    end: 4_000,
+                    content: "<v Roger>Hello</v>".to_owned(),
+                },
+                Track {
+                    start: 3_605_500,
+                    end: 3_607_000,
+                    content: "Bye".to_owned(),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn missing_header() {
+        let text = "00:01.000 --> 00:04.000\nHello\n";
+        assert_eq!(parse_webvtt(text), Err(SubtitlesError::MissingHeader));
+    }
+}
@@ -3,6 +3,8 @@ use either::Either;
 use semver::Version;
 use serde_derive::*;
 use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
 
 // Resource descriptors
 // those define how a resource may be requested
@@ -174,10 +176,183 @@ impl Manifest {
         };
         // types MUST contain type_name
         // and if there is id_prefixes, our id should start with at least one of them
-        let is_types_match = types.map_or(false, |types| types.contains(type_name));
+        let is_types_match = types.is_some_and(|types| types.contains(type_name));
         let is_id_match = id_prefixes.map_or(true, |prefixes| {
             prefixes.iter().any(|pref| id.starts_with(pref))
         });
         is_types_match && is_id_match
     }
+    pub fn validate(&self) -> Vec<ManifestIssue> {
+        let mut issues = vec![];
+        if self.id.is_empty() {
+            issues.push(ManifestIssue::EmptyId);
+        }
+        if self.name.is_empty() {
+            issues.push(ManifestIssue::EmptyName);
+        }
+        if self.types.is_empty() {
+            issues.push(ManifestIssue::NoTypes);
+        }
+        if self.resources.is_empty() {
+            issues.push(ManifestIssue::NoResources);
+        }
+        if !are_id_prefixes_valid(&self.id_prefixes) {
+            issues.push(ManifestIssue::InvalidIdPrefixes { resource: None });
+        }
+        let mut resource_names = HashSet::new();
+        for resource in &self.resources {
+            if !resource_names.insert(resource.name()) {
+                issues.push(ManifestIssue::DuplicateResource {
+                    name: resource.name().to_owned(),
+                });
+            }
+            if let ManifestResource::Full {
+                name, id_prefixes, ..
+            } = resource
+            {
+                if !are_id_prefixes_valid(id_prefixes) {
+                    issues.push(ManifestIssue::InvalidIdPrefixes {
+                        resource: Some(name.to_owned()),
+                    });
+                }
+            }
+        }
+        if resource_names.contains("catalog") && self.catalogs.is_empty() {
+            issues.push(ManifestIssue::CatalogResourceWithoutCatalogs);
+        }
+        let mut catalog_keys = HashSet::new();
+        // The add-on catalogs are a separate resource, so they may reuse the keys of the catalogs
+        let all_catalogs = self
+            .catalogs
+            .iter()
+            .map(|cat| (false, cat))
+            .chain(self.addon_catalogs.iter().map(|cat| (true, cat)));
+        for (is_addon_catalog, cat) in all_catalogs {
+            let type_name = &cat.type_name;
+            let id = &cat.id;
+            if !catalog_keys.insert((is_addon_catalog, type_name, id)) {
+                issues.push(ManifestIssue::DuplicateCatalog {
+                    type_name: type_name.to_owned(),
+                    id: id.to_owned(),
+                });
+            }
+            if !is_addon_catalog && !self.types.contains(type_name) {
+                issues.push(ManifestIssue::CatalogTypeNotInTypes {
+                    type_name: type_name.to_owned(),
+                    id: id.to_owned(),
+                });
+            }
+            for extra in cat.extra_iter() {
+                let has_options = extra.options.iter().flatten().next().is_some();
+                if extra.is_required && !has_options {
+                    issues.push(ManifestIssue::RequiredExtraWithoutOptions {
+                        type_name: type_name.to_owned(),
+                        id: id.to_owned(),
+                        name: extra.name.to_owned(),
+                    });
+                }
+            }
+        }
+        issues
+    }
+}
+
+// Problems with a manifest, found by Manifest::validate
+// Fatal issues make the add-on unusable (or ambiguous), the rest only affect parts of it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "issue", content = "args", rename_all = "camelCase")]
+pub enum ManifestIssue {
+    EmptyId,
+    EmptyName,
+    NoTypes,
+    NoResources,
+    // The id_prefixes of the manifest (resource None) or of a resource are empty,
+    // or contain an empty prefix
+    InvalidIdPrefixes {
+        resource: Option<String>,
+    },
+    DuplicateResource {
+        name: String,
+    },
+    DuplicateCatalog {
+        type_name: String,
+        id: String,
+    },
+    CatalogTypeNotInTypes {
+        type_name: String,
+        id: String,
+    },
+    CatalogResourceWithoutCatalogs,
+    // Such catalogs can't be requested, since there's nothing to default the prop to
+    RequiredExtraWithoutOptions {
+        type_name: String,
+        id: String,
+        name: String,
+    },
+}
+impl ManifestIssue {
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            ManifestIssue::EmptyId
+                | ManifestIssue::NoResources
+                | ManifestIssue::DuplicateResource { .. }
+                | ManifestIssue::DuplicateCatalog { .. }
+        )
+    }
+}
+impl fmt::Display for ManifestIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ManifestIssue::EmptyId => write!(f, "id is empty"),
+            ManifestIssue::EmptyName => write!(f, "name is empty"),
+            ManifestIssue::NoTypes => write!(f, "types is empty"),
+            ManifestIssue::NoResources => write!(f, "resources is empty"),
+            ManifestIssue::InvalidIdPrefixes { resource: None } => {
+                write!(f, "idPrefixes is empty or contains an empty prefix")
+            }
+            ManifestIssue::InvalidIdPrefixes {
+                resource: Some(name),
+            } => write!(
+                f,
+                "idPrefixes of resource {} is empty or contains an empty prefix",
+                name
+            ),
+            ManifestIssue::DuplicateResource { name } => {
+                write!(f, "resource {} is declared more than once", name)
+            }
+            ManifestIssue::DuplicateCatalog { type_name, id } => {
+                write!(f, "catalog {}/{} is declared more than once", type_name, id)
+            }
+            ManifestIssue::CatalogTypeNotInTypes { type_name, id } => write!(
+                f,
+                "catalog {}/{} has a type which is not in types",
+                type_name, id
+            ),
+            ManifestIssue::CatalogResourceWithoutCatalogs => {
+                write!(
+                    f,
+                    "the catalog resource is declared, but there are no catalogs"
+                )
+            }
+            ManifestIssue::RequiredExtraWithoutOptions {
+                type_name,
+                id,
+                name,
+            } => write!(
+                f,
+                "catalog {}/{} requires {}, but has no options for it",
+                type_name, id, name
+            ),
+        }
+    }
+}
+
+// Not having id_prefixes is fine, but an empty list would match nothing,
+// and an empty prefix would match everything
+fn are_id_prefixes_valid(id_prefixes: &Option<Vec<String>>) -> bool {
+    match id_prefixes {
+        Some(prefixes) => !prefixes.is_empty() && prefixes.iter().all(|p| !p.is_empty()),
+        None => true,
+    }
 }
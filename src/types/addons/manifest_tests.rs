@@ -1,7 +1,8 @@
 #[cfg(test)]
 mod tests {
     use super::super::{
-        Manifest, ManifestCatalog, ManifestExtra, ManifestExtraProp, ManifestResource, ResourceRef,
+        Manifest, ManifestCatalog, ManifestExtra, ManifestExtraProp, ManifestIssue,
+        ManifestResource, ResourceRef,
     };
 
     fn sample_manifest(
@@ -158,4 +159,99 @@ mod tests {
         assert!(!catalog.is_extra_supported(&[]));
         assert!(catalog.is_extra_supported(&foo));
     }
+
+    #[test]
+    fn validate_valid() {
+        let mut manifest = sample_manifest(
+            vec![
+                ManifestResource::Short("catalog".into()),
+                ManifestResource::Short("stream".into()),
+            ],
+            Some(vec!["tt".into()]),
+        );
+        manifest.catalogs = vec![get_catalog(ManifestExtra::Full {
+            props: vec![ManifestExtraProp {
+                name: "genre".into(),
+                is_required: true,
+                options: Some(vec!["Drama".into()]),
+                ..Default::default()
+            }],
+        })];
+        assert_eq!(manifest.validate(), vec![]);
+    }
+
+    #[test]
+    fn validate_issues() {
+        let manifest = sample_manifest(vec![], Some(vec![]));
+        let issues = manifest.validate();
+        assert_eq!(
+            issues,
+            vec![
+                ManifestIssue::NoResources,
+                ManifestIssue::InvalidIdPrefixes { resource: None },
+            ]
+        );
+        assert!(issues[0].is_fatal());
+        assert!(!issues[1].is_fatal());
+
+        let mut manifest = sample_manifest(
+            vec![
+                ManifestResource::Short("catalog".into()),
+                ManifestResource::Full {
+                    name: "stream".into(),
+                    types: None,
+                    id_prefixes: Some(vec!["".into()]),
+                },
+            ],
+            None,
+        );
+        assert_eq!(
+            manifest.validate(),
+            vec![
+                ManifestIssue::InvalidIdPrefixes {
+                    resource: Some("stream".into())
+                },
+                ManifestIssue::CatalogResourceWithoutCatalogs,
+            ]
+        );
+
+        let required = ManifestExtra::Short {
+            required: vec!["genre".into()],
+            supported: vec!["genre".into()],
+        };
+        let mut series_catalog = get_catalog(ManifestExtra::default());
+        series_catalog.type_name = "series".into();
+        manifest.resources = vec![ManifestResource::Short("catalog".into())];
+        manifest.catalogs = vec![
+            get_catalog(ManifestExtra::default()),
+            get_catalog(required),
+            series_catalog,
+        ];
+        // The same keys are fine for the add-on catalogs
+        manifest.addon_catalogs = vec![get_catalog(ManifestExtra::default())];
+        let issues = manifest.validate();
+        assert_eq!(
+            issues,
+            vec![
+                ManifestIssue::DuplicateCatalog {
+                    type_name: "movie".into(),
+                    id: "top".into()
+                },
+                ManifestIssue::RequiredExtraWithoutOptions {
+                    type_name: "movie".into(),
+                    id: "top".into(),
+                    name: "genre".into()
+                },
+                ManifestIssue::CatalogTypeNotInTypes {
+                    type_name: "series".into(),
+                    id: "top".into()
+                },
+            ]
+        );
+        assert_eq!(
+            issues.iter().filter(|i| i.is_fatal()).count(),
+            1,
+            "only the duplicate is fatal"
+        );
+    }
 }
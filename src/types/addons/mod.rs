@@ -6,6 +6,7 @@ pub use self::resource_ref::*;
 use crate::types::{MetaDetail, MetaPreview, Stream, SubtitlesSource};
 mod manifest_tests;
 use derive_more::*;
+use itertools::Itertools;
 
 pub type TransportUrl = String;
 
@@ -16,11 +17,59 @@ pub struct DescriptorFlags {
     pub official: bool,
     #[serde(default)]
     pub protected: bool,
+    // Disabled add-ons stay installed, but they are not requested
+    #[serde(default)]
+    pub disabled: bool,
+    // How the catalogs of the add-on are shown on the board, see board_catalogs; those are in
+    // the flags, so that they're synced with the add-on collection
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub catalogs: Vec<CatalogPrefs>,
     #[serde(flatten)]
     #[serde(default)]
     pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
+impl DescriptorFlags {
+    pub fn catalog_prefs(&self, type_name: &str, id: &str) -> Option<&CatalogPrefs> {
+        self.catalogs
+            .iter()
+            .find(|prefs| prefs.type_name == type_name && prefs.id == id)
+    }
+    pub fn catalog_prefs_mut(&mut self, type_name: &str, id: &str) -> &mut CatalogPrefs {
+        let position = self
+            .catalogs
+            .iter()
+            .position(|prefs| prefs.type_name == type_name && prefs.id == id);
+        let idx = match position {
+            Some(idx) => idx,
+            None => {
+                self.catalogs.push(CatalogPrefs {
+                    type_name: type_name.to_owned(),
+                    id: id.to_owned(),
+                    ..Default::default()
+                });
+                self.catalogs.len() - 1
+            }
+        };
+        &mut self.catalogs[idx]
+    }
+}
+
+// The user preferences for one catalog of an add-on
+#[derive(Default, PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogPrefs {
+    #[serde(rename = "type")]
+    pub type_name: String,
+    pub id: String,
+    #[serde(default)]
+    pub hidden: bool,
+    // The place on the board, among the catalogs of all add-ons; it's set for all of them
+    // once the board is reordered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+}
+
 #[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DescriptorPreview {
@@ -66,7 +115,8 @@ pub enum ResourceResponse {
         // NOTE: we are not putting this in Option<>, since that way it gives us a valid
         // fallback to all of the previous variants, therefore resulting in inaccurate err messages
         // To support other /meta/ responses (meta extensions), we should make a MetaExt variant
-        meta: MetaDetail,
+        // Boxed, since it's a lot bigger than the other variants
+        meta: Box<MetaDetail>,
     },
     Streams {
         streams: Vec<Stream>,
@@ -79,10 +129,27 @@ pub enum ResourceResponse {
     },
 }
 
+// Models keep the meta itself (e.g. ItemsGroup<MetaDetail>), rather than the box
+impl std::convert::TryFrom<ResourceResponse> for MetaDetail {
+    type Error = <Box<MetaDetail> as std::convert::TryFrom<ResourceResponse>>::Error;
+    fn try_from(resp: ResourceResponse) -> Result<Self, Self::Error> {
+        Box::<MetaDetail>::try_from(resp).map(|meta| *meta)
+    }
+}
+
+// Caching hints which addons may send alongside any resource response
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheHints {
+    // In seconds
+    pub cache_max_age: Option<u64>,
+}
+
 // This is going from the most general to the most concrete aggregation request
 #[derive(Debug, Clone)]
 pub enum AggrRequest<'a> {
     // @TODO should AllCatalogs have optional resource and type_name?
+    // The catalogs on the board, see board_catalogs
     AllCatalogs { extra: &'a Vec<ExtraProp> },
     AllOfResource(ResourceRef),
 }
@@ -94,37 +161,31 @@ impl AggrRequest<'_> {
         match &self {
             AggrRequest::AllCatalogs { extra } => {
                 // create a request for each catalog that matches the required extra properties
-                addons
-                    .iter()
-                    .map(|addon| {
-                        addon
-                            .manifest
-                            .catalogs
-                            .iter()
-                            .filter(|cat| cat.is_extra_supported(&extra))
-                            .map(move |cat| {
-                                (
-                                    addon,
-                                    ResourceRequest::new(
-                                        &addon.transport_url,
-                                        ResourceRef::with_extra(
-                                            "catalog",
-                                            &cat.type_name,
-                                            &cat.id,
-                                            extra,
-                                        ),
-                                    ),
-                                )
-                            })
+                board_catalogs(addons)
+                    .into_iter()
+                    .filter(|(addon, cat)| {
+                        let is_hidden = addon
+                            .flags
+                            .catalog_prefs(&cat.type_name, &cat.id)
+                            .is_some_and(|prefs| prefs.hidden);
+                        !is_hidden && cat.is_extra_supported(&extra)
+                    })
+                    .map(|(addon, cat)| {
+                        (
+                            addon,
+                            ResourceRequest::new(
+                                &addon.transport_url,
+                                ResourceRef::with_extra("catalog", &cat.type_name, &cat.id, extra),
+                            ),
+                        )
                     })
-                    .flatten()
                     .collect()
             }
             AggrRequest::AllOfResource(path) => {
                 // filter all addons that match the path
                 addons
                     .iter()
-                    .filter(|addon| addon.manifest.is_supported(&path))
+                    .filter(|addon| !addon.flags.disabled && addon.manifest.is_supported(&path))
                     .map(|addon| {
                         (
                             addon,
@@ -136,3 +197,21 @@ impl AggrRequest<'_> {
         }
     }
 }
+
+// The catalogs of all enabled add-ons, in the order they are shown on the board (including hidden ones):
+// the catalogs with a position come first, by their position, and the rest follow in the order of the add-ons
+pub fn board_catalogs(addons: &[Descriptor]) -> Vec<(&Descriptor, &ManifestCatalog)> {
+    addons
+        .iter()
+        .filter(|addon| !addon.flags.disabled)
+        .flat_map(|addon| addon.manifest.catalogs.iter().map(move |cat| (addon, cat)))
+        // the sort is stable, so the order of the add-ons is kept for the rest
+        .sorted_by_key(|(addon, cat)| {
+            addon
+                .flags
+                .catalog_prefs(&cat.type_name, &cat.id)
+                .and_then(|prefs| prefs.position)
+                .unwrap_or(usize::MAX)
+        })
+        .collect()
+}
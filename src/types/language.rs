@@ -0,0 +1,191 @@
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+// (ISO 639-1, ISO 639-2/B, ISO 639-2/T, English name)
+// Only the languages which are likely to be used for audio or subtitles are listed
+const LANGUAGES: &[(&str, &str, &str, &str)] = &[
+    ("af", "afr", "afr", "Afrikaans"),
+    ("sq", "alb", "sqi", "Albanian"),
+    ("am", "amh", "amh", "Amharic"),
+    ("ar", "ara", "ara", "Arabic"),
+    ("hy", "arm", "hye", "Armenian"),
+    ("az", "aze", "aze", "Azerbaijani"),
+    ("eu", "baq", "eus", "Basque"),
+    ("be", "bel", "bel", "Belarusian"),
+    ("bn", "ben", "ben", "Bengali"),
+    ("bs", "bos", "bos", "Bosnian"),
+    ("br", "bre", "bre", "Breton"),
+    ("bg", "bul", "bul", "Bulgarian"),
+    ("my", "bur", "mya", "Burmese"),
+    ("ca", "cat", "cat", "Catalan"),
+    ("zh", "chi", "zho", "Chinese"),
+    ("hr", "hrv", "hrv", "Croatian"),
+    ("cs", "cze", "ces", "Czech"),
+    ("da", "dan", "dan", "Danish"),
+    ("nl", "dut", "nld", "Dutch"),
+    ("en", "eng", "eng", "English"),
+    ("eo", "epo", "epo", "Esperanto"),
+    ("et", "est", "est", "Estonian"),
+    ("fi", "fin", "fin", "Finnish"),
+    ("fr", "fre", "fra", "French"),
+    ("gl", "glg", "glg", "Galician"),
+    ("ka", "geo", "kat", "Georgian"),
+    ("de", "ger", "deu", "German"),
+    ("el", "gre", "ell", "Greek"),
+    ("gu", "guj", "guj", "Gujarati"),
+    ("he", "heb", "heb", "Hebrew"),
+    ("hi", "hin", "hin", "Hindi"),
+    ("hu", "hun", "hun", "Hungarian"),
+    ("is", "ice", "isl", "Icelandic"),
+    ("id", "ind", "ind", "Indonesian"),
+    ("ga", "gle", "gle", "Irish"),
+    ("it", "ita", "ita", "Italian"),
+    ("ja", "jpn", "jpn", "Japanese"),
+    ("kn", "kan", "kan", "Kannada"),
+    ("kk", "kaz", "kaz", "Kazakh"),
+    ("km", "khm", "khm", "Khmer"),
+    ("ko", "kor", "kor", "Korean"),
+    ("ku", "kur", "kur", "Kurdish"),
+    ("la", "lat", "lat", "Latin"),
+    ("lv", "lav", "lav", "Latvian"),
+    ("lt", "lit", "lit", "Lithuanian"),
+    ("lb", "ltz", "ltz", "Luxembourgish"),
+    ("mk", "mac", "mkd", "Macedonian"),
+    ("ms", "may", "msa", "Malay"),
+    ("ml", "mal", "mal", "Malayalam"),
+    ("mt", "mlt", "mlt", "Maltese"),
+    ("mi", "mao", "mri", "Maori"),
+    ("mr", "mar", "mar", "Marathi"),
+    ("mn", "mon", "mon", "Mongolian"),
+    ("ne", "nep", "nep", "Nepali"),
+    ("no", "nor", "nor", "Norwegian"),
+    ("nb", "nob", "nob", "Norwegian Bokmål"),
+    ("nn", "nno", "nno", "Norwegian Nynorsk"),
+    ("oc", "oci", "oci", "Occitan"),
+    ("fa", "per", "fas", "Persian"),
+    ("pl", "pol", "pol", "Polish"),
+    ("pt", "por", "por", "Portuguese"),
+    ("pa", "pan", "pan", "Punjabi"),
+    ("ro", "rum", "ron", "Romanian"),
+    ("ru", "rus", "rus", "Russian"),
+    ("sr", "srp", "srp", "Serbian"),
+    ("si", "sin", "sin", "Sinhala"),
+    ("sk", "slo", "slk", "Slovak"),
+    ("sl", "slv", "slv", "Slovenian"),
+    ("so", "som", "som", "Somali"),
+    ("es", "spa", "spa", "Spanish"),
+    ("sw", "swa", "swa", "Swahili"),
+    ("sv", "swe", "swe", "Swedish"),
+    ("tl", "tgl", "tgl", "Tagalog"),
+    ("ta", "tam", "tam", "Tamil"),
+    ("te", "tel", "tel", "Telugu"),
+    ("th", "tha", "tha", "Thai"),
+    ("bo", "tib", "bod", "Tibetan"),
+    ("tr", "tur", "tur", "Turkish"),
+    ("uk", "ukr", "ukr", "Ukrainian"),
+    ("ur", "urd", "urd", "Urdu"),
+    ("uz", "uzb", "uzb", "Uzbek"),
+    ("vi", "vie", "vie", "Vietnamese"),
+    ("cy", "wel", "cym", "Welsh"),
+    ("yi", "yid", "yid", "Yiddish"),
+    ("zu", "zul", "zul", "Zulu"),
+];
+
+// A language, normalized to its ISO 639-2/B code, which is also what it's serialized as
+// Codes we don't know about (e.g. "pob", used by OpenSubtitles) are kept as they are,
+// so that they can still be compared and grouped by
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Language(String);
+
+impl Language {
+    // Accepts ISO 639-1, ISO 639-2/B and ISO 639-2/T codes, and English names; case insensitive
+    pub fn parse(s: &str) -> Self {
+        let s = s.trim().to_lowercase();
+        match LANGUAGES.iter().find(|(iso_1, iso_2b, iso_2t, name)| {
+            *iso_1 == s || *iso_2b == s || *iso_2t == s || name.to_lowercase() == s
+        }) {
+            Some((_, iso_2b, _, _)) => Language((*iso_2b).to_owned()),
+            None => Language(s),
+        }
+    }
+    fn entry(&self) -> Option<&'static (&'static str, &'static str, &'static str, &'static str)> {
+        LANGUAGES.iter().find(|(_, iso_2b, _, _)| *iso_2b == self.0)
+    }
+    pub fn is_known(&self) -> bool {
+        self.entry().is_some()
+    }
+    // ISO 639-2/B, or the original code if the language is not known
+    pub fn code(&self) -> &str {
+        &self.0
+    }
+    pub fn iso639_1(&self) -> Option<&'static str> {
+        self.entry().map(|(iso_1, _, _, _)| *iso_1)
+    }
+    pub fn iso639_2t(&self) -> Option<&'static str> {
+        self.entry().map(|(_, _, iso_2t, _)| *iso_2t)
+    }
+    pub fn name(&self) -> Option<&'static str> {
+        self.entry().map(|(_, _, _, name)| *name)
+    }
+}
+
+impl FromStr for Language {
+    type Err = Infallible;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Language::parse(s))
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name().unwrap_or(&self.0))
+    }
+}
+
+impl Serialize for Language {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Language {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|s| Language::parse(&s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes() {
+        let eng = Language::parse("eng");
+        assert_eq!(Language::parse("en"), eng);
+        assert_eq!(Language::parse("EN"), eng);
+        assert_eq!(Language::parse("english"), eng);
+        assert_eq!(Language::parse("fra"), Language::parse("fre"));
+        assert_eq!(Language::parse("deu").code(), "ger");
+        assert_eq!(Language::parse("de").iso639_2t(), Some("deu"));
+        assert_eq!(Language::parse("ger").iso639_1(), Some("de"));
+        assert_eq!(Language::parse("bg").to_string(), "Bulgarian");
+    }
+
+    #[test]
+    fn unknown_languages() {
+        let pob = Language::parse("POB");
+        assert!(!pob.is_known());
+        assert_eq!(pob.code(), "pob");
+        assert_eq!(pob.name(), None);
+        assert_eq!(pob.to_string(), "pob");
+    }
+
+    #[test]
+    fn serde() {
+        let lang: Language = serde_json::from_str("\"en\"").unwrap();
+        assert_eq!(serde_json::to_string(&lang).unwrap(), "\"eng\"");
+    }
+}
@@ -1,4 +1,4 @@
-use crate::types::PosterShape;
+use crate::types::{MetaDetail, PosterShape, Video, WatchedBitField, WatchedState};
 use chrono::serde::ts_milliseconds;
 use chrono::{DateTime, Utc};
 use serde::de::IntoDeserializer;
@@ -6,13 +6,16 @@ use serde::{Deserialize, Serialize};
 
 // Reference: https://github.com/Stremio/stremio-api/blob/master/types/libraryItem.go
 
+// A video counts as watched once that much of its duration has been played
+pub const WATCHED_THRESHOLD_COEF: f64 = 0.7;
+
 #[derive(Debug, Deserialize, Eq, PartialEq)]
 pub struct LibItemModified(
     pub String,
     #[serde(with = "ts_milliseconds")] pub DateTime<Utc>,
 );
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Ord, PartialOrd)]
 #[serde(rename_all = "camelCase")]
 pub struct LibItemState {
     #[serde(deserialize_with = "empty_string_as_none")]
@@ -26,9 +29,9 @@ pub struct LibItemState {
     pub duration: u64,
     #[serde(rename = "video_id", deserialize_with = "empty_string_as_none")]
     pub video_id: Option<String>,
-    // @TODO bitfield, special type
+    // Which videos are watched, see WatchedBitField
     #[serde(deserialize_with = "empty_string_as_none")]
-    pub watched: Option<String>,
+    pub watched: Option<WatchedState>,
     // release date of last observed video
     #[serde(deserialize_with = "empty_string_as_none", default)]
     pub last_vid_released: Option<DateTime<Utc>>,
@@ -67,13 +70,43 @@ pub struct LibItem {
 }
 
 impl LibItem {
+    // A new item, which is not watched yet
+    pub fn from_meta(meta: &MetaDetail, now: DateTime<Utc>) -> Self {
+        LibItem {
+            id: meta.id.to_owned(),
+            removed: false,
+            temp: false,
+            ctime: Some(now),
+            mtime: now,
+            state: LibItemState::default(),
+            name: meta.name.to_owned(),
+            type_name: meta.type_name.to_owned(),
+            poster: meta.poster.to_owned(),
+            poster_shape: meta.poster_shape.to_owned(),
+            background: meta.background.to_owned(),
+            logo: meta.logo.to_owned(),
+            year: meta.release_info.to_owned(),
+        }
+    }
+    pub fn watched_bitfield(&self, videos: &[Video]) -> WatchedBitField {
+        let video_ids = videos.iter().map(|video| video.id.to_owned()).collect();
+        match &self.state.watched {
+            Some(WatchedState::Parsed(field)) => WatchedBitField::from_field(field, video_ids),
+            // A field we can't read is treated as nothing watched
+            Some(WatchedState::Raw(_)) | None => WatchedBitField::new(video_ids),
+        }
+    }
     pub fn should_persist(&self) -> bool {
         !self.temp
     }
+    // Whether the item is ever pushed; the changes the user makes to such items always are
+    pub fn can_push(&self) -> bool {
+        self.should_persist() && self.type_name != "other"
+    }
+    // Whether the item is pushed when syncing
     // Must return a result that's in a logical conjunction (&&) with .should_persist()
     pub fn should_push(&self) -> bool {
-        self.should_persist()
-            && self.type_name != "other"
+        self.can_push()
             && if self.removed {
                 self.state.overall_time_watched > 60_000
             } else {
@@ -88,7 +121,8 @@ impl LibItem {
             && self.state.video_id.is_some()
             && self.state.video_id.as_ref() != Some(&self.id);
 
-        !self.removed && (is_resumable || is_with_nextvid)
+        // Temp items are not in the library, but they're watched (see the Player model)
+        (!self.removed || self.temp) && (is_resumable || is_with_nextvid)
     }
 }
 
@@ -123,4 +157,35 @@ mod test {
             "poster deserialized correctly"
         );
     }
+
+    #[test]
+    pub fn unparseable_watched_is_kept() {
+        let state = |watched: &str| -> LibItemState {
+            serde_json::from_value(serde_json::json!({
+                "lastWatched": "",
+                "timeWatched": 0,
+                "timeOffset": 0,
+                "overallTimeWatched": 0,
+                "timesWatched": 0,
+                "flaggedWatched": 0,
+                "duration": 0,
+                "video_id": "",
+                "watched": watched,
+                "noNotif": false
+            }))
+            .unwrap()
+        };
+        assert_eq!(state("").watched, None);
+        assert!(matches!(
+            state("tt2934286:1:5:5:eJyTZwAAAEAAIA==").watched,
+            Some(WatchedState::Parsed(_))
+        ));
+        let raw = state("tt2934286:1:5:v2:unknown");
+        assert_eq!(
+            raw.watched,
+            Some(WatchedState::Raw("tt2934286:1:5:v2:unknown".to_owned()))
+        );
+        let serialized = serde_json::to_value(&raw).unwrap();
+        assert_eq!(serialized["watched"], "tt2934286:1:5:v2:unknown");
+    }
 }
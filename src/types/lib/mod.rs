@@ -3,3 +3,6 @@ pub use item::*;
 
 mod bucket;
 pub use bucket::*;
+
+mod watched_bitfield;
+pub use watched_bitfield::*;
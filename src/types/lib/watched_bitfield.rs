@@ -0,0 +1,231 @@
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::error::Error;
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+// LibItemState::watched, as stored by Stremio v4: "{anchor video id}:{anchor length}:{bitfield}"
+// The bitfield is zlib-compressed and base64 encoded; bit N (least significant first) is for the
+// Nth video. Only the order of the videos is stored, so the anchor (the last watched video and
+// how many videos there were up to it) is what aligns the bits if videos are added later
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WatchedField {
+    pub anchor_video: String,
+    pub anchor_length: usize,
+    pub bitfield: Vec<u8>,
+}
+
+impl FromStr for WatchedField {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // The anchor is a video id, which usually contains ':' as well
+        let mut parts = s.rsplitn(3, ':');
+        let (bitfield, anchor_length, anchor_video) =
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(bitfield), Some(anchor_length), Some(anchor_video)) => {
+                    (bitfield, anchor_length, anchor_video)
+                }
+                _ => return Err("watched field must have 3 parts".into()),
+            };
+        let compressed = base64::decode(bitfield)?;
+        let mut bitfield = vec![];
+        ZlibDecoder::new(&compressed[..]).read_to_end(&mut bitfield)?;
+        Ok(WatchedField {
+            anchor_video: anchor_video.to_owned(),
+            anchor_length: anchor_length.parse()?,
+            bitfield,
+        })
+    }
+}
+
+impl fmt::Display for WatchedField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        // Writing to a Vec can't fail
+        encoder
+            .write_all(&self.bitfield)
+            .expect("zlib write failed");
+        let compressed = encoder.finish().expect("zlib write failed");
+        write!(
+            f,
+            "{}:{}:{}",
+            self.anchor_video,
+            self.anchor_length,
+            base64::encode(&compressed)
+        )
+    }
+}
+
+impl Serialize for WatchedField {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for WatchedField {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// LibItemState::watched; a field we can't parse (e.g. written by a newer version) is kept as it is,
+// so that it's sent back unchanged
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum WatchedState {
+    Parsed(WatchedField),
+    Raw(String),
+}
+
+// Which of the videos (e.g. the episodes of a series) are watched; the video ids are in the
+// order of MetaDetail::videos
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedBitField {
+    video_ids: Vec<String>,
+    watched: Vec<bool>,
+}
+
+impl WatchedBitField {
+    pub fn new(video_ids: Vec<String>) -> Self {
+        let watched = vec![false; video_ids.len()];
+        WatchedBitField { video_ids, watched }
+    }
+    // If the anchor video is gone, we can't tell which bit is which, so nothing is watched
+    pub fn from_field(field: &WatchedField, video_ids: Vec<String>) -> Self {
+        let mut bitfield = WatchedBitField::new(video_ids);
+        let anchor_idx = match bitfield
+            .video_ids
+            .iter()
+            .position(|id| *id == field.anchor_video)
+        {
+            Some(anchor_idx) => anchor_idx,
+            None => return bitfield,
+        };
+        // How many videos were added before the anchor since the field was saved
+        let shift = anchor_idx as isize + 1 - field.anchor_length as isize;
+        for (idx, watched) in bitfield.watched.iter_mut().enumerate() {
+            let field_idx = idx as isize - shift;
+            *watched = field_idx >= 0 && get_bit(&field.bitfield, field_idx as usize);
+        }
+        bitfield
+    }
+    pub fn to_field(&self) -> Option<WatchedField> {
+        // Nothing watched is anchored at the last video
+        let anchor_idx = self
+            .watched
+            .iter()
+            .rposition(|watched| *watched)
+            .or_else(|| self.video_ids.len().checked_sub(1))?;
+        let mut bitfield = vec![0; self.watched.len().div_ceil(8)];
+        for (idx, _) in self.watched.iter().enumerate().filter(|(_, w)| **w) {
+            bitfield[idx / 8] |= 1 << (idx % 8);
+        }
+        Some(WatchedField {
+            anchor_video: self.video_ids[anchor_idx].to_owned(),
+            anchor_length: anchor_idx + 1,
+            bitfield,
+        })
+    }
+    pub fn is_watched(&self, video_id: &str) -> bool {
+        self.video_ids
+            .iter()
+            .position(|id| id == video_id)
+            .is_some_and(|idx| self.watched[idx])
+    }
+    // Returns false if the video is unknown
+    pub fn set_watched(&mut self, video_id: &str, is_watched: bool) -> bool {
+        match self.video_ids.iter().position(|id| id == video_id) {
+            Some(idx) => {
+                self.watched[idx] = is_watched;
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn watched_videos(&self) -> impl Iterator<Item = &String> {
+        self.video_ids
+            .iter()
+            .zip(self.watched.iter())
+            .filter(|(_, watched)| **watched)
+            .map(|(id, _)| id)
+    }
+}
+
+fn get_bit(bitfield: &[u8], idx: usize) -> bool {
+    bitfield
+        .get(idx / 8)
+        .is_some_and(|byte| byte & (1 << (idx % 8)) != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video_ids(count: usize) -> Vec<String> {
+        (1..=count)
+            .map(|ep| format!("tt2934286:1:{}", ep))
+            .collect()
+    }
+
+    #[test]
+    fn parses_stremio_v4_fields() {
+        // Stored by Stremio v4, with the first 5 episodes watched
+        let field: WatchedField = "tt2934286:1:5:5:eJyTZwAAAEAAIA==".parse().unwrap();
+        assert_eq!(field.anchor_video, "tt2934286:1:5");
+        assert_eq!(field.anchor_length, 5);
+        let bitfield = WatchedBitField::from_field(&field, video_ids(10));
+        assert!(bitfield.is_watched("tt2934286:1:5"));
+        assert!(!bitfield.is_watched("tt2934286:1:6"));
+        assert_eq!(bitfield.watched_videos().count(), 5);
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut bitfield = WatchedBitField::new(video_ids(20));
+        assert!(bitfield.set_watched("tt2934286:1:3", true));
+        assert!(bitfield.set_watched("tt2934286:1:12", true));
+        assert!(
+            !bitfield.set_watched("tt2934286:2:1", true),
+            "unknown video"
+        );
+        let serialized = bitfield.to_field().unwrap().to_string();
+        assert!(serialized.starts_with("tt2934286:1:12:12:"));
+        let field: WatchedField = serialized.parse().unwrap();
+        assert_eq!(WatchedBitField::from_field(&field, video_ids(20)), bitfield);
+    }
+
+    #[test]
+    fn survives_new_videos() {
+        let mut bitfield = WatchedBitField::new(video_ids(5));
+        bitfield.set_watched("tt2934286:1:2", true);
+        let field = bitfield.to_field().unwrap();
+        // Appended
+        let appended = WatchedBitField::from_field(&field, video_ids(8));
+        assert_eq!(
+            appended.watched_videos().collect::<Vec<_>>(),
+            vec!["tt2934286:1:2"]
+        );
+        // Inserted before the watched one
+        let mut ids = video_ids(5);
+        ids.insert(0, "tt2934286:0:1".to_owned());
+        let inserted = WatchedBitField::from_field(&field, ids);
+        assert_eq!(
+            inserted.watched_videos().collect::<Vec<_>>(),
+            vec!["tt2934286:1:2"]
+        );
+        // The anchor is gone
+        let gone = WatchedBitField::from_field(&field, vec!["tt2934286:2:1".to_owned()]);
+        assert_eq!(gone.watched_videos().count(), 0);
+    }
+
+    #[test]
+    fn invalid_fields() {
+        assert!("".parse::<WatchedField>().is_err());
+        assert!("tt1:1:1:notbase64!".parse::<WatchedField>().is_err());
+        assert!("tt1:1:x:eJyTZwAAAEAAIA==".parse::<WatchedField>().is_err());
+    }
+}
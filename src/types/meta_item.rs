@@ -120,6 +120,25 @@ pub struct MetaDetail {
     pub trailer: Option<Stream>,
 }
 
+impl MetaDetail {
+    // The episode after this one, in season/episode order; specials (season 0) are skipped,
+    // and videos which are not episodes don't have a next one
+    pub fn next_video(&self, video_id: &str) -> Option<&Video> {
+        let current = self
+            .videos
+            .iter()
+            .find(|video| video.id == video_id)?
+            .series_info
+            .as_ref()?;
+        self.videos
+            .iter()
+            .filter_map(|video| Some((video.series_info.as_ref()?, video)))
+            .filter(|(info, _)| info.season != 0 && info.order() > current.order())
+            .min_by_key(|(info, _)| info.order())
+            .map(|(_, video)| video)
+    }
+}
+
 // https://github.com/Stremio/stremio-addon-sdk/blob/master/docs/api/responses/meta.md#video-object
 #[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -143,3 +162,50 @@ pub struct SeriesInfo {
     pub season: u32,
     pub episode: u32,
 }
+impl SeriesInfo {
+    fn order(&self) -> (u32, u32) {
+        (self.season, self.episode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn next_video() {
+        let video = |id: &str, series_info: Option<(u32, u32)>| {
+            let mut video = json!({
+                "id": id,
+                "title": id,
+                "released": "2019-01-01T00:00:00.000Z"
+            });
+            if let Some((season, episode)) = series_info {
+                video["season"] = json!(season);
+                video["episode"] = json!(episode);
+            }
+            video
+        };
+        // Not in order, like some add-ons return them
+        let meta: MetaDetail = serde_json::from_value(json!({
+            "id": "tt1",
+            "type": "series",
+            "videos": [
+                video("s2e1", Some((2, 1))),
+                video("s1e2", Some((1, 2))),
+                video("special", Some((0, 1))),
+                video("s1e1", Some((1, 1))),
+                video("trailer", None)
+            ]
+        }))
+        .unwrap();
+        let next = |id| meta.next_video(id).map(|video| video.id.as_str());
+        assert_eq!(next("s1e1"), Some("s1e2"));
+        assert_eq!(next("s1e2"), Some("s2e1"), "next season");
+        assert_eq!(next("s2e1"), None, "last episode");
+        assert_eq!(next("special"), Some("s1e1"));
+        assert_eq!(next("trailer"), None);
+        assert_eq!(next("unknown"), None);
+    }
+}
@@ -9,3 +9,6 @@ pub use self::lib::*;
 
 mod stream;
 pub use self::stream::*;
+
+mod language;
+pub use self::language::*;
@@ -1,9 +1,19 @@
+use crate::types::Language;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
 use serde_derive::*;
 use serde_hex::{SerHex, Strict};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{Read, Write};
 
-// * Deduplication can be achieved by simple comparison (Eq)
-// * @TODO Sorting
-// * @TODO Serializing/deserializing streams with gzip+base64, for URLs
+// * Deduplication: streams from different add-ons are the same if their dedup_key is
+// * Sorting: see the Streams model, which ranks them by quality and the user preferences
+// * Serializing/deserializing streams for URLs: see to_url_param/from_url_param
 
 #[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -16,20 +26,91 @@ pub struct Stream {
     pub thumbnail: Option<String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub subtitles: Vec<SubtitlesSource>,
-    // @TODO better data structure
-    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
-    pub behavior_hints: serde_json::Map<String, serde_json::Value>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_behavior_hints",
+        skip_serializing_if = "StreamBehaviorHints::is_empty"
+    )]
+    pub behavior_hints: StreamBehaviorHints,
+}
+
+// Deserialized with deserialize_behavior_hints, so that one malformed hint doesn't fail the stream
+#[derive(PartialEq, Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamBehaviorHints {
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub not_web_ready: bool,
+    // Streams of the same binge group can be autoplayed one after another (e.g. same release group)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binge_group: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_headers: Option<StreamProxyHeaders>,
+    // ISO 3166-1 alpha-3 country codes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country_whitelist: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    // In bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_size: Option<u64>,
+    // Hints we don't know about (or can't parse) are kept, so that they're not lost when serializing
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+impl StreamBehaviorHints {
+    pub fn is_empty(&self) -> bool {
+        *self == StreamBehaviorHints::default()
+    }
+}
+
+// Headers to use when proxying the stream through the streaming server
+// Sorted, so that the same stream always gives the same URL param
+#[derive(PartialEq, Clone, Debug, Default, Deserialize, Serialize)]
+pub struct StreamProxyHeaders {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub request: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub response: BTreeMap<String, String>,
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+// Each known hint is parsed on its own: a malformed one falls back to the default, and its raw
+// value stays with the other hints
+fn deserialize_behavior_hints<'de, D>(deserializer: D) -> Result<StreamBehaviorHints, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    fn take<T: DeserializeOwned>(other: &mut Map<String, Value>, name: &str) -> Option<T> {
+        let value = other.remove(name)?;
+        match serde_json::from_value(value.to_owned()) {
+            Ok(hint) => Some(hint),
+            Err(_) => {
+                other.insert(name.to_owned(), value);
+                None
+            }
+        }
+    }
+    let mut other = Map::deserialize(deserializer)?;
+    Ok(StreamBehaviorHints {
+        not_web_ready: take(&mut other, "notWebReady").unwrap_or_default(),
+        binge_group: take(&mut other, "bingeGroup"),
+        proxy_headers: take(&mut other, "proxyHeaders"),
+        country_whitelist: take(&mut other, "countryWhitelist"),
+        filename: take(&mut other, "filename"),
+        video_size: take(&mut other, "videoSize"),
+        other,
+    })
 }
 
 impl Stream {
     pub fn is_web_ready(&self) -> bool {
-        if self.behavior_hints.get("notWebReady") == Some(&serde_json::Value::Bool(true)) {
+        if self.behavior_hints.not_web_ready {
             return false;
         }
-        match &self.source {
-            StreamSource::Url { url } if url.starts_with("https:") => true,
-            _ => false,
-        }
+        matches!(&self.source, StreamSource::Url { url } if url.starts_with("https:"))
     }
     pub fn is_p2p(&self) -> bool {
         match &self.source {
@@ -38,6 +119,102 @@ impl Stream {
             _ => false,
         }
     }
+    // Add-ons don't have a dedicated property for that, so it's guessed from the title
+    pub fn quality(&self) -> StreamQuality {
+        let title = match &self.title {
+            Some(title) => title.to_lowercase(),
+            None => return StreamQuality::Unknown,
+        };
+        title
+            .split(|c: char| !c.is_alphanumeric())
+            .filter_map(|token| match token {
+                "2160p" | "4k" | "uhd" => Some(StreamQuality::UHD),
+                "1080p" | "fhd" => Some(StreamQuality::FullHD),
+                "720p" | "hd" => Some(StreamQuality::HD),
+                "576p" | "480p" | "360p" | "sd" => Some(StreamQuality::SD),
+                "cam" | "camrip" | "hdcam" | "ts" | "telesync" => Some(StreamQuality::Cam),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(StreamQuality::Unknown)
+    }
+    // JSON, gzipped and then base64 encoded, so that it can be put in URLs (e.g. deep links)
+    pub fn to_url_param(&self) -> String {
+        let json = serde_json::to_vec(self).expect("stream must serialize");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        // Writing to a Vec can't fail
+        encoder.write_all(&json).expect("gzip write failed");
+        let gzipped = encoder.finish().expect("gzip write failed");
+        base64::encode_config(&gzipped, base64::URL_SAFE_NO_PAD)
+    }
+    pub fn from_url_param(param: &str) -> Result<Self, Box<dyn Error>> {
+        let gzipped = base64::decode_config(param, base64::URL_SAFE_NO_PAD)?;
+        let mut json = vec![];
+        GzDecoder::new(&gzipped[..]).read_to_end(&mut json)?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+    // Different files of a torrent are different streams; magnet links are the same as
+    // the torrent they point to, without a file_idx
+    pub fn dedup_key(&self) -> String {
+        match &self.source {
+            StreamSource::Url { url } => magnet_info_hash(url).unwrap_or_else(|| url.to_owned()),
+            StreamSource::Torrent {
+                info_hash,
+                file_idx: Some(file_idx),
+            } => format!("{}/{}", hex_info_hash(info_hash), file_idx),
+            StreamSource::Torrent { info_hash, .. } => hex_info_hash(info_hash),
+            StreamSource::External { external_url } => external_url.to_owned(),
+            StreamSource::PlayerFrame { player_frame_url } => player_frame_url.to_owned(),
+        }
+    }
+}
+
+fn hex_info_hash(info_hash: &[u8; 20]) -> String {
+    info_hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// The info hash of a magnet link, in lowercase hex; it's either in hex or in base32 in the link
+fn magnet_info_hash(url: &str) -> Option<String> {
+    let query = url.strip_prefix("magnet:?")?;
+    let hash = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("xt=urn:btih:"))?;
+    match hash.len() {
+        40 if hash.chars().all(|c| c.is_ascii_hexdigit()) => Some(hash.to_lowercase()),
+        32 => base32_to_hex(hash),
+        _ => None,
+    }
+}
+
+fn base32_to_hex(base32: &str) -> Option<String> {
+    let mut bits = 0u32;
+    let mut bits_len = 0;
+    let mut hex = String::new();
+    for c in base32.chars().map(|c| c.to_ascii_uppercase()) {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            '2'..='7' => c as u32 - '2' as u32 + 26,
+            _ => return None,
+        };
+        bits = (bits << 5 | value) & 0xff;
+        bits_len += 5;
+        while bits_len >= 4 {
+            bits_len -= 4;
+            hex.push(std::char::from_digit((bits >> bits_len) & 0xf, 16)?);
+        }
+    }
+    Some(hex)
+}
+
+// Ordered from the worst to the best
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Deserialize, Serialize)]
+pub enum StreamQuality {
+    Unknown,
+    Cam,
+    SD,
+    HD,
+    FullHD,
+    UHD,
 }
 
 #[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
@@ -67,8 +244,7 @@ pub enum StreamSource {
 #[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
 pub struct SubtitlesSource {
     pub id: String,
-    // @TODO: ISO 639-2
-    pub lang: String,
+    pub lang: Language,
     pub url: String,
 }
 
@@ -79,7 +255,7 @@ mod test {
     #[test]
     pub fn deserialize() {
         let stream_json = "{\"infoHash\":\"07a9de9750158471c3302e4e95edb1107f980fa6\",\"fileIdx\":1,\"title\":\"test stream\"}";
-        let stream: Stream = serde_json::from_str(&stream_json).unwrap();
+        let stream: Stream = serde_json::from_str(stream_json).unwrap();
         assert_eq!(
             stream,
             Stream {
@@ -98,11 +274,155 @@ mod test {
         )
     }
 
+    #[test]
+    pub fn behavior_hints() {
+        let stream_json = r#"{
+            "url": "https://example.com/video.mp4",
+            "behaviorHints": {
+                "notWebReady": true,
+                "bingeGroup": "group-1",
+                "proxyHeaders": { "request": { "User-Agent": "Stremio" } },
+                "videoSize": 1024,
+                "someFutureHint": [1, 2]
+            }
+        }"#;
+        let stream: Stream = serde_json::from_str(stream_json).unwrap();
+        let hints = &stream.behavior_hints;
+        assert!(hints.not_web_ready);
+        assert!(!stream.is_web_ready());
+        assert_eq!(hints.binge_group, Some("group-1".to_owned()));
+        assert_eq!(
+            hints
+                .proxy_headers
+                .as_ref()
+                .and_then(|h| h.request.get("User-Agent")),
+            Some(&"Stremio".to_owned())
+        );
+        assert_eq!(hints.video_size, Some(1024));
+        assert_eq!(
+            hints.other.get("someFutureHint"),
+            Some(&serde_json::json!([1, 2]))
+        );
+        // Unknown hints survive serializing
+        let value = serde_json::to_value(&stream).unwrap();
+        assert_eq!(
+            value["behaviorHints"]["someFutureHint"],
+            serde_json::json!([1, 2])
+        );
+        assert_eq!(value["behaviorHints"]["notWebReady"], true);
+    }
+
+    #[test]
+    pub fn malformed_behavior_hints() {
+        let stream_json = r#"{
+            "url": "https://example.com/video.mp4",
+            "behaviorHints": {
+                "notWebReady": "true",
+                "bingeGroup": 1,
+                "filename": "video.mp4"
+            }
+        }"#;
+        let stream: Stream = serde_json::from_str(stream_json).unwrap();
+        let hints = &stream.behavior_hints;
+        assert!(!hints.not_web_ready);
+        assert_eq!(hints.binge_group, None);
+        assert_eq!(hints.filename, Some("video.mp4".to_owned()));
+        // The raw values are sent back unchanged
+        let value = serde_json::to_value(&stream).unwrap();
+        assert_eq!(
+            value["behaviorHints"],
+            serde_json::json!({
+                "notWebReady": "true",
+                "bingeGroup": 1,
+                "filename": "video.mp4"
+            })
+        );
+    }
+
+    #[test]
+    pub fn url_param_round_trip() {
+        let stream_json = r#"{
+            "infoHash": "07a9de9750158471c3302e4e95edb1107f980fa6",
+            "fileIdx": 1,
+            "title": "test stream 1080p",
+            "subtitles": [{ "id": "1", "lang": "eng", "url": "https://example.com/sub.srt" }],
+            "behaviorHints": { "notWebReady": true, "bingeGroup": "group-1" }
+        }"#;
+        let stream: Stream = serde_json::from_str(stream_json).unwrap();
+        let param = stream.to_url_param();
+        assert!(
+            param
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+            "param is URL safe"
+        );
+        assert_eq!(Stream::from_url_param(&param).unwrap(), stream);
+        assert!(Stream::from_url_param("not a stream").is_err());
+    }
+
+    #[test]
+    pub fn quality_from_title() {
+        let with_title = |title: &str| Stream {
+            title: Some(title.into()),
+            thumbnail: None,
+            subtitles: Default::default(),
+            behavior_hints: Default::default(),
+            source: StreamSource::Url {
+                url: "https://example.com/video.mp4".into(),
+            },
+        };
+        assert_eq!(
+            with_title("Movie 1080p WEB-DL").quality(),
+            StreamQuality::FullHD
+        );
+        assert_eq!(
+            with_title("Movie.2019.4K.HDR").quality(),
+            StreamQuality::UHD
+        );
+        assert_eq!(with_title("[720p] Movie").quality(), StreamQuality::HD);
+        assert_eq!(with_title("Movie HDCAM").quality(), StreamQuality::Cam);
+        assert_eq!(with_title("Movie").quality(), StreamQuality::Unknown);
+        // The best one wins, e.g. for "1080p (upscaled from 720p)"
+        assert_eq!(
+            with_title("1080p upscaled from 720p").quality(),
+            StreamQuality::FullHD
+        );
+    }
+
+    #[test]
+    pub fn dedup_key() {
+        // The info hash can't be deserialized from a serde_json::Value, only from a string
+        let stream = |source: serde_json::Value| -> Stream {
+            serde_json::from_str(&source.to_string()).unwrap()
+        };
+        let hash = "07a9de9750158471c3302e4e95edb1107f980fa6";
+        let torrent = stream(serde_json::json!({ "infoHash": hash }));
+        assert_eq!(torrent.dedup_key(), hash);
+        assert_eq!(
+            stream(serde_json::json!({ "infoHash": hash, "fileIdx": 1 })).dedup_key(),
+            format!("{}/1", hash),
+            "other files of the torrent are other streams"
+        );
+        let magnet = |hash: &str| {
+            stream(serde_json::json!({ "url": format!("magnet:?xt=urn:btih:{}&dn=Movie", hash) }))
+        };
+        assert_eq!(magnet(&hash.to_uppercase()).dedup_key(), hash);
+        assert_eq!(
+            magnet("A6U55F2QCWCHDQZQFZHJL3NRCB7ZQD5G").dedup_key(),
+            hash,
+            "base32 info hash"
+        );
+        assert_eq!(
+            stream(serde_json::json!({ "url": "https://example.com/video.mp4" })).dedup_key(),
+            "https://example.com/video.mp4"
+        );
+    }
+
     #[test]
     pub fn deserialize_no_fileidx() {
         let stream_json =
             "{\"infoHash\":\"07a9de9750158471c3302e4e95edb1107f980fa6\",\"title\":\"test stream\"}";
-        let stream: Stream = serde_json::from_str(&stream_json).unwrap();
+        let stream: Stream = serde_json::from_str(stream_json).unwrap();
         assert_eq!(
             stream,
             Stream {
@@ -0,0 +1,177 @@
+use super::*;
+use crate::state_types::*;
+use crate::types::addons::Descriptor;
+use chrono::{TimeZone, Utc};
+use serde_json::json;
+use std::time::Duration;
+use stremio_derive::Model;
+use tokio::runtime::current_thread::run;
+
+const CATALOG_URL: &str = "https://addon.example.com/catalog/movie/top.json";
+
+#[derive(Model, Debug, Default)]
+struct Model {
+    ctx: Ctx<EnvMock>,
+    catalogs: CatalogGrouped,
+}
+
+fn addon() -> Descriptor {
+    sample_addon(
+        "addon.example.com",
+        json!({
+            "types": ["movie"],
+            "resources": ["catalog"],
+            "catalogs": [{ "type": "movie", "id": "top" }]
+        }),
+    )
+}
+
+fn model_with_addon() -> Model {
+    let mut model = Model::default();
+    model.ctx.content.addons = vec![addon()];
+    model
+}
+
+fn load_catalogs() -> Msg {
+    Action::Load(ActionLoad::CatalogGrouped { extra: vec![] }).into()
+}
+
+fn assert_ready(runtime: &Runtime<EnvMock, Model>) {
+    let model = runtime.app.read().unwrap();
+    assert_eq!(model.catalogs.groups.len(), 1);
+    match &model.catalogs.groups[0].content {
+        Loadable::Ready(metas) => assert_eq!(metas.len(), 1),
+        x => panic!("catalog group not ready, but instead: {:?}", x),
+    }
+}
+
+#[test]
+fn cached_response_is_rendered_and_revalidated_when_stale() {
+    EnvMock::reset();
+    EnvMock::respond(
+        "GET",
+        CATALOG_URL,
+        &json!({
+            "metas": [{ "id": "tt1", "type": "movie", "name": "Movie" }],
+            "cacheMaxAge": 3600
+        }),
+    );
+    EnvMock::skip_delays(true);
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(model_with_addon(), 1000);
+    run(runtime.dispatch(&load_catalogs()));
+    assert_ready(&runtime);
+    assert_eq!(EnvMock::request_urls(), vec![CATALOG_URL]);
+    let stored: AddonCache =
+        EnvMock::get_storage_sync("addon_cache").expect("addon_cache is stored");
+    assert!(stored
+        .get(runtime.app.read().unwrap().catalogs.groups[0].addon_req())
+        .is_some());
+
+    // Fresh: rendered right away, without requesting it again
+    let fx = runtime.dispatch(&load_catalogs());
+    assert_ready(&runtime);
+    run(fx);
+    assert_eq!(EnvMock::requests().len(), 1, "no new request was made");
+
+    // Stale: still rendered right away, but revalidated
+    EnvMock::set_now(Utc.with_ymd_and_hms(2020, 1, 1, 2, 0, 0).unwrap());
+    let fx = runtime.dispatch(&load_catalogs());
+    assert_ready(&runtime);
+    run(fx);
+    assert_eq!(EnvMock::request_urls(), vec![CATALOG_URL, CATALOG_URL]);
+    assert_ready(&runtime);
+}
+
+#[test]
+fn cache_is_loaded_from_storage() {
+    EnvMock::reset();
+    EnvMock::respond(
+        "GET",
+        CATALOG_URL,
+        &json!({
+            "metas": [{ "id": "tt1", "type": "movie", "name": "Movie" }],
+            "cacheMaxAge": 3600
+        }),
+    );
+    // Not the periodic jobs of the ctx, which would never stop
+    EnvMock::skip_delays_up_to(Duration::from_secs(60));
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(model_with_addon(), 1000);
+    run(runtime.dispatch(&load_catalogs()));
+
+    // A new runtime, which only knows about the persisted cache
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    runtime.app.write().unwrap().ctx.content.addons = vec![addon()];
+    run(runtime.dispatch(&load_catalogs()));
+    assert_ready(&runtime);
+    assert_eq!(EnvMock::requests().len(), 1, "no new request was made");
+}
+
+#[test]
+fn cache_is_persisted_after_a_delay() {
+    EnvMock::reset();
+    EnvMock::respond(
+        "GET",
+        CATALOG_URL,
+        &json!({
+            "metas": [{ "id": "tt1", "type": "movie", "name": "Movie" }],
+            "cacheMaxAge": 3600
+        }),
+    );
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(model_with_addon(), 1000);
+    run(runtime.dispatch(&load_catalogs()));
+    // Rendered right away, but not persisted yet
+    assert_ready(&runtime);
+    assert!(EnvMock::get_storage_sync::<AddonCache>("addon_cache").is_none());
+
+    // The delay failed, so the next response schedules it again
+    EnvMock::skip_delays(true);
+    EnvMock::set_now(Utc.with_ymd_and_hms(2020, 1, 1, 2, 0, 0).unwrap());
+    run(runtime.dispatch(&load_catalogs()));
+    assert!(EnvMock::get_storage_sync::<AddonCache>("addon_cache").is_some());
+}
+
+#[test]
+fn responses_of_a_removed_addon_are_dropped() {
+    EnvMock::reset();
+    EnvMock::respond(
+        "GET",
+        CATALOG_URL,
+        &json!({
+            "metas": [{ "id": "tt1", "type": "movie", "name": "Movie" }],
+            "cacheMaxAge": 3600
+        }),
+    );
+    EnvMock::skip_delays(true);
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(model_with_addon(), 1000);
+    run(runtime.dispatch(&load_catalogs()));
+    let req = runtime.app.read().unwrap().catalogs.groups[0]
+        .addon_req()
+        .to_owned();
+    assert!(runtime
+        .app
+        .read()
+        .unwrap()
+        .ctx
+        .addon_cache
+        .get(&req)
+        .is_some());
+
+    run(runtime.dispatch(
+        &Action::AddonOp(ActionAddon::Remove {
+            transport_url: addon().transport_url,
+        })
+        .into(),
+    ));
+    assert!(runtime
+        .app
+        .read()
+        .unwrap()
+        .ctx
+        .addon_cache
+        .get(&req)
+        .is_none());
+    let stored: AddonCache =
+        EnvMock::get_storage_sync("addon_cache").expect("addon_cache is stored");
+    assert!(stored.get(&req).is_none(), "not loaded next time");
+}
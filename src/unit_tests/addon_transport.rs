@@ -0,0 +1,108 @@
+use super::*;
+use crate::addon_transport::*;
+use crate::state_types::*;
+use crate::types::addons::{Descriptor, ResourceRef, ResourceResponse};
+use futures::Future;
+use serde_json::json;
+
+#[test]
+fn transport_manifests() {
+    EnvMock::reset();
+    let manifest_url = "https://addon.example.com/manifest.json";
+    let legacy_url = "https://legacy.example.com/stremioget/stremio/v1";
+    EnvMock::respond(
+        "GET",
+        manifest_url,
+        &json!({
+            "id": "com.example.addon",
+            "version": "1.0.0",
+            "name": "Example",
+            "types": ["movie"],
+            "resources": ["catalog"],
+            "catalogs": [{ "type": "movie", "id": "top" }]
+        }),
+    );
+    EnvMock::respond(
+        "GET",
+        &format!(
+            "{}/q.json?b={}",
+            legacy_url, "eyJwYXJhbXMiOltdLCJtZXRob2QiOiJtZXRhIiwiaWQiOjEsImpzb25ycGMiOiIyLjAifQ=="
+        ),
+        &json!({
+            "result": {
+                "manifest": {
+                    "id": "com.example.legacy",
+                    "version": "1.0.0",
+                    "name": "Legacy",
+                    "methods": ["meta.find", "meta.get"],
+                    "types": ["movie", "series"]
+                }
+            }
+        }),
+    );
+    let manifest = AddonHTTPTransport::<EnvMock>::from_url(manifest_url)
+        .manifest()
+        .wait()
+        .expect("failed getting the manifest");
+    assert_eq!(manifest.id, "com.example.addon");
+    assert_eq!(manifest.catalogs.len(), 1);
+    let legacy = AddonHTTPTransport::<EnvMock>::from_url(legacy_url)
+        .manifest()
+        .wait()
+        .expect("failed getting the legacy manifest");
+    assert_eq!(legacy.id, "com.example.legacy");
+    // Without sorts, there's a top catalog for each type
+    let catalogs = legacy
+        .catalogs
+        .iter()
+        .map(|c| (c.type_name.as_str(), c.id.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(catalogs, vec![("movie", "top"), ("series", "top")]);
+}
+
+#[test]
+fn get_videos() {
+    EnvMock::reset();
+    let videos = (1..=3)
+        .map(|episode| {
+            json!({
+                "id": format!("tt0386676:1:{}", episode),
+                "title": "Episode",
+                "released": "2005-03-24T00:00:00.000Z",
+                "season": 1,
+                "episode": episode
+            })
+        })
+        .collect::<Vec<_>>();
+    EnvMock::respond(
+        "GET",
+        "https://addon.example.com/meta/series/tt0386676.json",
+        &json!({
+            "meta": { "id": "tt0386676", "type": "series", "name": "The Office", "videos": videos }
+        }),
+    );
+    let resp = AddonHTTPTransport::<EnvMock>::from_url("https://addon.example.com/manifest.json")
+        .get(&ResourceRef::without_extra("meta", "series", "tt0386676"))
+        .wait()
+        .expect("failed getting metadata");
+    match resp {
+        ResourceResponse::Meta { meta } => assert_eq!(meta.videos.len(), 3, "has videos"),
+        _ => panic!("unexpected response"),
+    }
+}
+
+#[test]
+fn addon_collection() {
+    EnvMock::reset();
+    let collection_url = "https://api.strem.io/addonscollection.json";
+    let addons = CtxContent::default().addons;
+    EnvMock::respond("GET", collection_url, &addons);
+    let req = Request::get(collection_url)
+        .body(())
+        .expect("builder cannot fail");
+    let collection = EnvMock::fetch_serde::<_, Vec<Descriptor>>(req)
+        .wait()
+        .expect("failed getting the addon collection");
+    assert!(!collection.is_empty(), "has addons");
+    assert_eq!(collection, addons, "survives a round-trip");
+}
@@ -0,0 +1,121 @@
+use super::*;
+use crate::state_types::*;
+use crate::types::addons::{ResourceRef, ResourceRequest};
+use serde_json::json;
+use stremio_derive::Model;
+use tokio::runtime::current_thread::run;
+
+#[derive(Model, Debug, Default)]
+struct Model {
+    ctx: Ctx<EnvMock>,
+    discovery: AddonsDiscovery,
+}
+
+fn manifest(id: &str, version: &str, types: serde_json::Value) -> serde_json::Value {
+    json!({
+        "id": id,
+        "version": version,
+        "name": id,
+        "types": types,
+        "resources": ["stream"]
+    })
+}
+
+fn install(runtime: &Runtime<EnvMock, Model>, manifest: serde_json::Value, transport_url: &str) {
+    let descriptor = serde_json::from_value(json!({
+        "manifest": manifest,
+        "transportUrl": transport_url
+    }))
+    .expect("descriptor must deserialize");
+    run(runtime.dispatch(&Action::AddonOp(ActionAddon::Install(Box::new(descriptor))).into()));
+}
+
+fn entries(runtime: &Runtime<EnvMock, Model>) -> Vec<(String, bool, bool)> {
+    let model = runtime.app.read().unwrap();
+    model
+        .discovery
+        .entries
+        .iter()
+        .map(|e| {
+            (
+                e.addon.manifest.id.to_owned(),
+                e.is_installed,
+                e.is_upgradable,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn marks_installed_and_upgradable() {
+    EnvMock::reset();
+    let store_url = "https://store.addon.test/manifest.json";
+    let mut store_manifest = manifest("org.store", "1.0.0", json!([]));
+    store_manifest["resources"] = json!(["addon_catalog"]);
+    store_manifest["addonCatalogs"] = json!([{ "type": "all", "id": "community" }]);
+    EnvMock::respond(
+        "GET",
+        "https://store.addon.test/addon_catalog/all/community.json",
+        &json!({
+            "addons": [
+                {
+                    "manifest": manifest("org.one", "1.1.0", json!(["movie"])),
+                    "transportUrl": "https://one.addon.test/manifest.json"
+                },
+                {
+                    "manifest": manifest("org.two", "1.0.0", json!(["series"])),
+                    "transportUrl": "https://two.addon.test/manifest.json"
+                }
+            ]
+        }),
+    );
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    install(&runtime, store_manifest, store_url);
+    install(
+        &runtime,
+        manifest("org.one", "1.0.0", json!(["movie"])),
+        "https://one.addon.test/manifest.json",
+    );
+
+    let request = ResourceRequest::new(
+        store_url,
+        ResourceRef::without_extra("addon_catalog", "all", "community"),
+    );
+    let load = |type_filter: Option<&str>| {
+        run(runtime.dispatch(
+            &Action::Load(ActionLoad::AddonsDiscovery {
+                request: request.to_owned(),
+                type_filter: type_filter.map(ToOwned::to_owned),
+            })
+            .into(),
+        ));
+    };
+    load(None);
+    assert_eq!(
+        entries(&runtime),
+        vec![
+            ("org.one".to_owned(), true, true),
+            ("org.two".to_owned(), false, false),
+        ]
+    );
+    // Not loaded by the catalogs of metas
+    let other = ResourceRequest::new(
+        store_url,
+        ResourceRef::without_extra("catalog", "movie", "top"),
+    );
+    run(runtime.dispatch(&Action::Load(ActionLoad::CatalogFiltered(other)).into()));
+    assert_eq!(entries(&runtime).len(), 2);
+
+    load(Some("series"));
+    assert_eq!(
+        entries(&runtime),
+        vec![("org.two".to_owned(), false, false)]
+    );
+    install(
+        &runtime,
+        manifest("org.two", "1.0.0", json!(["series"])),
+        "https://two.addon.test/manifest.json",
+    );
+    assert_eq!(entries(&runtime), vec![("org.two".to_owned(), true, false)]);
+}
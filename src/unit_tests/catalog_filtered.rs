@@ -0,0 +1,214 @@
+use super::*;
+use crate::state_types::*;
+use crate::types::addons::{Descriptor, ResourceRef, ResourceRequest};
+use crate::types::MetaPreview;
+use futures::Future;
+use serde_json::json;
+use stremio_derive::Model;
+use tokio::runtime::current_thread::run;
+
+const TRANSPORT_URL: &str = "https://addon.example.com/manifest.json";
+
+#[derive(Model, Debug, Default)]
+struct Model {
+    ctx: Ctx<EnvMock>,
+    catalogs: CatalogFiltered<MetaPreview>,
+}
+
+fn addon() -> Descriptor {
+    sample_addon(
+        "addon.example.com",
+        json!({
+            "types": ["movie", "series"],
+            "resources": ["catalog"],
+            "catalogs": [
+                { "type": "movie", "id": "top", "extra": [{ "name": "skip" }] },
+                { "type": "series", "id": "top" },
+                {
+                    "type": "movie",
+                    "id": "genres",
+                    "extra": [{ "name": "genre", "isRequired": true, "options": ["Drama", "Comedy"] }]
+                }
+            ]
+        }),
+    )
+}
+
+fn sample_metas(len: usize) -> serde_json::Value {
+    let metas = (0..len)
+        .map(|i| json!({ "id": format!("tt{}", i), "type": "movie", "name": format!("Movie {}", i) }))
+        .collect::<Vec<_>>();
+    json!({ "metas": metas })
+}
+
+fn model_with_addon() -> Model {
+    let mut model = Model::default();
+    model.ctx.content.addons = vec![addon()];
+    model
+}
+
+#[test]
+fn load_first_and_next_page() {
+    EnvMock::reset();
+    EnvMock::respond(
+        "GET",
+        "https://addon.example.com/catalog/movie/top.json",
+        &sample_metas(100),
+    );
+    EnvMock::respond(
+        "GET",
+        "https://addon.example.com/catalog/movie/top/skip=100.json",
+        &sample_metas(10),
+    );
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(model_with_addon(), 1000);
+    let req = ResourceRequest::new(
+        TRANSPORT_URL,
+        ResourceRef::without_extra("catalog", "movie", "top"),
+    );
+    run(runtime.dispatch(&Action::Load(ActionLoad::CatalogFiltered(req.clone())).into()));
+
+    let state = runtime.app.read().unwrap().catalogs.to_owned();
+    assert_eq!(
+        EnvMock::request_urls(),
+        vec!["https://addon.example.com/catalog/movie/top.json"]
+    );
+    assert_eq!(state.selected, Some(req));
+    assert_eq!(
+        state
+            .types
+            .iter()
+            .map(|t| t.type_name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["movie", "series"]
+    );
+    // The genres catalog gets the first option of the required extra prop
+    assert_eq!(state.catalogs.len(), 3);
+    assert_eq!(
+        state.catalogs[2].load.path.get_extra_first_val("genre"),
+        Some("Drama")
+    );
+    match &state.content {
+        Loadable::Ready(metas) => assert_eq!(metas.len(), 100),
+        x => panic!("content is not Ready, but instead: {:?}", x),
+    }
+    assert_eq!(state.load_prev, None);
+    let load_next = state.load_next.expect("there should be a next page");
+    assert_eq!(load_next.path.get_extra_first_val("skip"), Some("100"));
+
+    run(runtime.dispatch(&Action::Load(ActionLoad::CatalogFiltered(load_next)).into()));
+    let state = runtime.app.read().unwrap().catalogs.to_owned();
+    match &state.content {
+        Loadable::Ready(metas) => assert_eq!(metas.len(), 10),
+        x => panic!("content is not Ready, but instead: {:?}", x),
+    }
+    assert_eq!(state.load_next, None, "last page");
+    assert_eq!(
+        state
+            .load_prev
+            .expect("there should be a prev page")
+            .path
+            .get_extra_first_val("skip"),
+        Some("0")
+    );
+}
+
+#[test]
+fn failed_request() {
+    EnvMock::reset();
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(model_with_addon(), 1000);
+    let req = ResourceRequest::new(
+        TRANSPORT_URL,
+        ResourceRef::without_extra("catalog", "series", "top"),
+    );
+    run(runtime.dispatch(&Action::Load(ActionLoad::CatalogFiltered(req)).into()));
+
+    let state = &runtime.app.read().unwrap().catalogs;
+    match &state.content {
+        Loadable::Err(CatalogError::Other(_)) => (),
+        x => panic!("content is not Err, but instead: {:?}", x),
+    }
+    assert_eq!(state.load_next, None);
+}
+
+#[test]
+fn slow_addon_times_out() {
+    EnvMock::reset();
+    EnvMock::respond_never("GET", "https://addon.example.com/catalog/series/top.json");
+    EnvMock::skip_delays(true);
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(model_with_addon(), 1000);
+    let req = ResourceRequest::new(
+        TRANSPORT_URL,
+        ResourceRef::without_extra("catalog", "series", "top"),
+    );
+    run(runtime.dispatch(&Action::Load(ActionLoad::CatalogFiltered(req)).into()));
+
+    let state = &runtime.app.read().unwrap().catalogs;
+    match &state.content {
+        Loadable::Err(CatalogError::Timeout) => (),
+        x => panic!("content is not a timeout, but instead: {:?}", x),
+    }
+}
+
+#[test]
+fn superseded_load_is_cancelled() {
+    EnvMock::reset();
+    EnvMock::respond_never("GET", "https://addon.example.com/catalog/series/top.json");
+    EnvMock::respond(
+        "GET",
+        "https://addon.example.com/catalog/movie/top.json",
+        &sample_metas(1),
+    );
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(model_with_addon(), 1000);
+    let slow_req = ResourceRequest::new(
+        TRANSPORT_URL,
+        ResourceRef::without_extra("catalog", "series", "top"),
+    );
+    let req = ResourceRequest::new(
+        TRANSPORT_URL,
+        ResourceRef::without_extra("catalog", "movie", "top"),
+    );
+    // The slow request would never finish, unless the second load cancels it
+    let slow_fx = runtime.dispatch(&Action::Load(ActionLoad::CatalogFiltered(slow_req)).into());
+    let fx = runtime.dispatch(&Action::Load(ActionLoad::CatalogFiltered(req.clone())).into());
+    run(slow_fx.join(fx).map(|_| ()));
+
+    let state = &runtime.app.read().unwrap().catalogs;
+    assert_eq!(state.selected, Some(req));
+    match &state.content {
+        Loadable::Ready(metas) => assert_eq!(metas.len(), 1),
+        x => panic!("content is not Ready, but instead: {:?}", x),
+    }
+}
+
+#[test]
+fn superseded_load_is_cancelled_with_a_cloned_model() {
+    EnvMock::reset();
+    EnvMock::respond_never("GET", "https://addon.example.com/catalog/series/top.json");
+    EnvMock::respond(
+        "GET",
+        "https://addon.example.com/catalog/movie/top.json",
+        &sample_metas(1),
+    );
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(model_with_addon(), 1000);
+    let slow_req = ResourceRequest::new(
+        TRANSPORT_URL,
+        ResourceRef::without_extra("catalog", "series", "top"),
+    );
+    let req = ResourceRequest::new(
+        TRANSPORT_URL,
+        ResourceRef::without_extra("catalog", "movie", "top"),
+    );
+    // The slow request would never finish, unless the second load cancels it
+    let slow_fx = runtime.dispatch(&Action::Load(ActionLoad::CatalogFiltered(slow_req)).into());
+    // A clone shares the effects handle, which must not keep the effects alive
+    let _cloned = runtime.app.read().unwrap().catalogs.to_owned();
+    let fx = runtime.dispatch(&Action::Load(ActionLoad::CatalogFiltered(req.clone())).into());
+    run(slow_fx.join(fx).map(|_| ()));
+
+    let state = &runtime.app.read().unwrap().catalogs;
+    assert_eq!(state.selected, Some(req));
+    match &state.content {
+        Loadable::Ready(metas) => assert_eq!(metas.len(), 1),
+        x => panic!("content is not Ready, but instead: {:?}", x),
+    }
+}
@@ -0,0 +1,195 @@
+use super::*;
+use crate::state_types::*;
+use serde_json::json;
+use stremio_derive::Model;
+use tokio::runtime::current_thread::run;
+
+#[derive(Model, Debug, Default)]
+struct Model {
+    ctx: Ctx<EnvMock>,
+    board: CatalogGrouped,
+}
+
+fn install(runtime: &Runtime<EnvMock, Model>, id: &str, catalogs: serde_json::Value) {
+    let descriptor = sample_addon(
+        &format!("{}.addon.test", id),
+        json!({
+            "id": id,
+            "types": ["movie", "series"],
+            "resources": ["catalog"],
+            "catalogs": catalogs
+        }),
+    );
+    run(runtime.dispatch(&Action::AddonOp(ActionAddon::Install(Box::new(descriptor))).into()));
+}
+
+// The catalogs of the test add-ons on the board, in order
+fn board(runtime: &Runtime<EnvMock, Model>) -> Vec<String> {
+    load_board(runtime, vec![])
+}
+
+fn load_board(runtime: &Runtime<EnvMock, Model>, extra: Vec<(String, String)>) -> Vec<String> {
+    run(runtime.dispatch(&Action::Load(ActionLoad::CatalogGrouped { extra }).into()));
+    let model = runtime.app.read().unwrap();
+    model
+        .board
+        .groups
+        .iter()
+        .map(|group| group.addon_req())
+        .filter(|req| req.base.ends_with(".addon.test/manifest.json"))
+        .map(|req| format!("{}/{}", req.path.type_name, req.path.id))
+        .collect()
+}
+
+#[test]
+fn catalogs_can_be_hidden_and_moved() {
+    EnvMock::reset();
+    EnvMock::respond_api("addonCollectionSet", &json!({ "success": true }));
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    // Logged in, so that the changes are pushed
+    runtime.app.write().unwrap().ctx.content.auth = serde_json::from_value(json!({
+        "key": "auth_key",
+        "user": {
+            "_id": "user_id",
+            "email": "user@stremio.com",
+            "fbId": null,
+            "avatar": null,
+            "lastModified": "2019-01-01T00:00:00.000Z",
+            "dateRegistered": "2019-01-01T00:00:00.000Z"
+        }
+    }))
+    .unwrap();
+    install(
+        &runtime,
+        "first",
+        json!([{ "type": "movie", "id": "top" }, { "type": "movie", "id": "new" }]),
+    );
+    install(
+        &runtime,
+        "second",
+        json!([{ "type": "series", "id": "top" }]),
+    );
+    assert_eq!(
+        board(&runtime),
+        vec!["movie/top", "movie/new", "series/top"]
+    );
+
+    let addon_op = |action: ActionAddon| run(runtime.dispatch(&Action::AddonOp(action).into()));
+    addon_op(ActionAddon::HideCatalog {
+        transport_url: "https://first.addon.test/manifest.json".into(),
+        type_name: "movie".into(),
+        id: "new".into(),
+    });
+    assert_eq!(board(&runtime), vec!["movie/top", "series/top"]);
+
+    addon_op(ActionAddon::MoveCatalog {
+        transport_url: "https://second.addon.test/manifest.json".into(),
+        type_name: "series".into(),
+        id: "top".into(),
+        position: 0,
+    });
+    assert_eq!(board(&runtime), vec!["series/top", "movie/top"]);
+
+    addon_op(ActionAddon::ShowCatalog {
+        transport_url: "https://first.addon.test/manifest.json".into(),
+        type_name: "movie".into(),
+        id: "new".into(),
+    });
+    assert_eq!(
+        board(&runtime),
+        vec!["series/top", "movie/top", "movie/new"]
+    );
+
+    // The order doesn't depend on the order of the add-ons anymore
+    addon_op(ActionAddon::MoveDown {
+        transport_url: "https://first.addon.test/manifest.json".into(),
+    });
+    assert_eq!(
+        board(&runtime),
+        vec!["series/top", "movie/top", "movie/new"]
+    );
+
+    // A catalog of a disabled add-on keeps its preferences
+    addon_op(ActionAddon::HideCatalog {
+        transport_url: "https://second.addon.test/manifest.json".into(),
+        type_name: "series".into(),
+        id: "top".into(),
+    });
+    addon_op(ActionAddon::Disable {
+        transport_url: "https://second.addon.test/manifest.json".into(),
+    });
+    addon_op(ActionAddon::MoveCatalog {
+        transport_url: "https://first.addon.test/manifest.json".into(),
+        type_name: "movie".into(),
+        id: "new".into(),
+        position: 0,
+    });
+    addon_op(ActionAddon::Enable {
+        transport_url: "https://second.addon.test/manifest.json".into(),
+    });
+    assert_eq!(board(&runtime), vec!["movie/new", "movie/top"]);
+
+    // The prefs are in the flags of the add-ons, which are pushed with every change
+    let pushes = EnvMock::requests()
+        .into_iter()
+        .filter(|req| req.url.ends_with("/api/addonCollectionSet"))
+        .collect::<Vec<_>>();
+    assert_eq!(pushes.len(), 10, "every change is pushed");
+    let stored: CtxContent = EnvMock::get_storage_sync("userData").expect("userData is stored");
+    assert_eq!(pushes.last().unwrap().body["addons"], json!(stored.addons));
+    let mut prefs = stored
+        .addons
+        .iter()
+        .filter(|addon| addon.transport_url.ends_with(".addon.test/manifest.json"))
+        .flat_map(|addon| addon.flags.catalogs.iter())
+        .collect::<Vec<_>>();
+    assert!(prefs.iter().all(|prefs| prefs.position.is_some()));
+    prefs.sort_by_key(|prefs| prefs.position);
+    let prefs = prefs
+        .iter()
+        .map(|prefs| format!("{}/{} {}", prefs.type_name, prefs.id, prefs.hidden))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        prefs,
+        vec!["movie/new false", "movie/top false", "series/top true"],
+        "the order of the board is kept"
+    );
+}
+
+#[test]
+fn search_only_loads_the_catalogs_that_support_it() {
+    EnvMock::reset();
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    install(
+        &runtime,
+        "first",
+        json!([
+            { "type": "movie", "id": "top" },
+            { "type": "movie", "id": "search", "extra": [{ "name": "search", "isRequired": true }] }
+        ]),
+    );
+    install(
+        &runtime,
+        "second",
+        json!([{ "type": "series", "id": "top", "extraSupported": ["search"] }]),
+    );
+    assert_eq!(
+        board(&runtime),
+        vec!["movie/top", "series/top"],
+        "catalogs that require search are not on the board"
+    );
+
+    let extra = vec![("search".to_owned(), "grand tour".to_owned())];
+    assert_eq!(
+        load_board(&runtime, extra.to_owned()),
+        vec!["movie/search", "series/top"]
+    );
+    let model = runtime.app.read().unwrap();
+    assert!(model
+        .board
+        .groups
+        .iter()
+        .all(|group| group.addon_req().path.extra == extra));
+}
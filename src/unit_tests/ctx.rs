@@ -0,0 +1,1039 @@
+use super::*;
+use crate::state_types::*;
+use crate::types::addons::{AggrRequest, Descriptor, ResourceRef};
+use crate::types::{LibBucket, LibItem, MetaDetail, WatchedState};
+use chrono::{TimeZone, Utc};
+use futures::{Future, Stream};
+use itertools::Itertools;
+use serde_json::json;
+use std::time::Duration;
+use stremio_derive::Model;
+use tokio::runtime::current_thread::run;
+
+#[derive(Model, Debug, Default)]
+struct Model {
+    ctx: Ctx<EnvMock>,
+    lib_recent: LibRecent,
+}
+
+// A movie with some progress, so it's in the continue watching
+fn watched_lib_item(id: &str) -> LibItem {
+    let mut item = sample_lib_item(id, "movie");
+    item.state.time_offset = 1000;
+    item
+}
+
+fn mock_login(key: &str, lib_items: &[LibItem]) {
+    EnvMock::respond_api(
+        "login",
+        &json!({
+            "authKey": key,
+            "user": {
+                "_id": "user_id",
+                "email": "user@stremio.com",
+                "fbId": null,
+                "avatar": null,
+                "lastModified": "2019-01-01T00:00:00.000Z",
+                "dateRegistered": "2019-01-01T00:00:00.000Z"
+            }
+        }),
+    );
+    EnvMock::respond_api(
+        "addonCollectionGet",
+        &json!({ "addons": [], "lastModified": "2019-01-01T00:00:00.000Z" }),
+    );
+    EnvMock::respond_api("datastoreGet", &lib_items);
+    // The library is synced when it's loaded from storage; nothing has changed since
+    let mtimes = lib_items
+        .iter()
+        .map(|item| json!([item.id, item.mtime.timestamp_millis()]))
+        .collect::<Vec<_>>();
+    EnvMock::respond_api("datastoreMeta", &mtimes);
+}
+
+#[test]
+fn load_ctx_from_empty_storage() {
+    EnvMock::reset();
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+
+    let model = runtime.app.read().unwrap();
+    assert!(model.ctx.is_loaded, "ctx is loaded");
+    assert_eq!(model.ctx.content, CtxContent::default(), "default content");
+    assert_eq!(
+        model.ctx.library,
+        LibraryLoadable::Ready(Default::default()),
+        "library is empty"
+    );
+    assert!(EnvMock::requests().is_empty(), "no requests were made");
+}
+
+#[test]
+fn login_pulls_addons_and_library() {
+    EnvMock::reset();
+    let item = watched_lib_item("tt0000001");
+    mock_login("auth_key", std::slice::from_ref(&item));
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    run(runtime.dispatch(
+        &Action::UserOp(ActionUser::Login {
+            email: "user@stremio.com".into(),
+            password: "password".into(),
+        })
+        .into(),
+    ));
+
+    let urls = EnvMock::request_urls();
+    assert_eq!(
+        urls,
+        vec![
+            "https://api.strem.io/api/login",
+            "https://api.strem.io/api/addonCollectionGet",
+            "https://api.strem.io/api/datastoreGet",
+        ],
+        "requests were made in the right order, without a sync after the full fetch"
+    );
+    let get_req = &EnvMock::requests()[2];
+    assert_eq!(get_req.body["authKey"], "auth_key");
+    assert_eq!(get_req.body["collection"], "libraryItem");
+    assert_eq!(get_req.body["all"], true);
+
+    let model = runtime.app.read().unwrap();
+    assert_eq!(
+        model.ctx.content.auth.as_ref().map(|a| a.key.as_str()),
+        Some("auth_key")
+    );
+    assert!(model.ctx.content.addons.is_empty(), "addons were replaced");
+    assert_eq!(model.ctx.library.get(&item.id), Some(&item));
+    assert_eq!(model.lib_recent.recent, vec![item], "item is in recent");
+    // The content and library are persisted
+    let stored: CtxContent = EnvMock::get_storage_sync("userData").expect("userData is stored");
+    assert_eq!(stored, model.ctx.content);
+    let stored: LibBucket =
+        EnvMock::get_storage_sync("recent_library").expect("recent_library is stored");
+    assert_eq!(stored.items.len(), 1);
+}
+
+#[test]
+fn lib_update_persists_and_pushes() {
+    EnvMock::reset();
+    mock_login("auth_key", &[]);
+    EnvMock::respond_api("datastorePut", &json!({ "success": true }));
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    run(runtime.dispatch(
+        &Action::UserOp(ActionUser::Login {
+            email: "user@stremio.com".into(),
+            password: "password".into(),
+        })
+        .into(),
+    ));
+
+    let item = sample_lib_item("tt0000002", "movie");
+    run(runtime.dispatch(&Action::UserOp(ActionUser::LibUpdate(item.clone())).into()));
+
+    let put_req = EnvMock::requests()
+        .into_iter()
+        .find(|r| r.url.ends_with("/api/datastorePut"))
+        .expect("datastorePut was requested");
+    assert_eq!(put_req.body["changes"][0]["_id"], "tt0000002");
+    let model = runtime.app.read().unwrap();
+    assert_eq!(model.ctx.library.get(&item.id), Some(&item));
+    assert!(
+        model.lib_recent.recent.is_empty(),
+        "not in continue watching"
+    );
+    let stored: LibBucket =
+        EnvMock::get_storage_sync("recent_library").expect("recent_library is stored");
+    assert_eq!(stored.items.get(&item.id), Some(&item));
+}
+
+#[test]
+fn anon_lib_update_is_only_persisted() {
+    EnvMock::reset();
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    let item = watched_lib_item("tt0000003");
+    run(runtime.dispatch(&Action::UserOp(ActionUser::LibUpdate(item.clone())).into()));
+
+    assert!(EnvMock::requests().is_empty(), "nothing is pushed");
+    // A new runtime loads the same library from storage
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    let model = runtime.app.read().unwrap();
+    assert_eq!(model.ctx.library.get(&item.id), Some(&item));
+    assert_eq!(model.lib_recent.recent, vec![item]);
+}
+
+#[test]
+fn logout_resets_the_content_and_library() {
+    EnvMock::reset();
+    let item = watched_lib_item("tt0000004");
+    mock_login("auth_key", std::slice::from_ref(&item));
+    EnvMock::respond_api("logout", &json!({ "success": true }));
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    let user_op = |action: ActionUser| run(runtime.dispatch(&Action::UserOp(action).into()));
+    user_op(ActionUser::Login {
+        email: "user@stremio.com".into(),
+        password: "password".into(),
+    });
+    user_op(ActionUser::Logout);
+
+    let logout_req = EnvMock::requests()
+        .into_iter()
+        .find(|r| r.url.ends_with("/api/logout"))
+        .expect("logout was requested");
+    assert_eq!(logout_req.body["authKey"], "auth_key");
+    let model = runtime.app.read().unwrap();
+    assert_eq!(model.ctx.content, CtxContent::default(), "logged out");
+    assert!(
+        !model.ctx.content.addons.is_empty(),
+        "has the default addons"
+    );
+    assert_eq!(
+        model.ctx.library,
+        LibraryLoadable::Ready(Default::default()),
+        "library is empty"
+    );
+    assert!(model.lib_recent.recent.is_empty(), "recent is empty");
+    let stored: CtxContent = EnvMock::get_storage_sync("userData").expect("userData is stored");
+    assert_eq!(stored, CtxContent::default());
+}
+
+fn sample_movie(id: &str) -> Box<MetaDetail> {
+    serde_json::from_value(json!({
+        "id": id,
+        "type": "movie",
+        "name": "Sample",
+        "poster": "https://example.com/poster.jpg",
+        "releaseInfo": "2019"
+    }))
+    .expect("sample meta must deserialize")
+}
+
+fn user_op(runtime: &Runtime<EnvMock, Model>, action: ActionUser) {
+    run(runtime.dispatch(&Action::UserOp(action).into()));
+}
+
+#[test]
+fn lib_item_lifecycle() {
+    EnvMock::reset();
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    let id = "tt0000004".to_owned();
+    let lib_item = || {
+        let model = runtime.app.read().unwrap();
+        model
+            .ctx
+            .library
+            .get(&id)
+            .cloned()
+            .expect("item is in the library")
+    };
+
+    let added = EnvMock::now();
+    user_op(&runtime, ActionUser::AddToLibrary(sample_movie(&id)));
+    let item = lib_item();
+    assert_eq!(item.ctime, Some(added));
+    assert_eq!(item.mtime, added);
+    assert_eq!(item.year, Some("2019".to_owned()));
+    assert!(!item.removed && !item.temp);
+
+    let later = Utc.with_ymd_and_hms(2020, 1, 2, 0, 0, 0).unwrap();
+    EnvMock::set_now(later);
+    user_op(&runtime, ActionUser::MarkAsWatched(id.to_owned(), true));
+    assert_eq!(lib_item().mtime, later, "mtime is bumped");
+    // Another change at the same time still wins
+    user_op(&runtime, ActionUser::ToggleNotifications(id.to_owned()));
+    let item = lib_item();
+    assert!(item.mtime > later);
+    assert_eq!(item.state.times_watched, 1);
+    assert_eq!(item.state.flagged_watched, 1);
+    assert!(item.state.no_notif);
+
+    user_op(&runtime, ActionUser::RemoveFromLibrary(id.to_owned()));
+    assert!(lib_item().removed);
+    // Adding it again keeps the state
+    user_op(&runtime, ActionUser::AddToLibrary(sample_movie(&id)));
+    let item = lib_item();
+    assert!(!item.removed);
+    assert_eq!(item.ctime, Some(added));
+    assert_eq!(item.state.times_watched, 1);
+
+    user_op(&runtime, ActionUser::MarkAsWatched(id.to_owned(), false));
+    assert_eq!(lib_item().state.times_watched, 0);
+    // Unknown items are ignored
+    user_op(
+        &runtime,
+        ActionUser::RemoveFromLibrary("tt0000005".to_owned()),
+    );
+    assert!(runtime
+        .app
+        .read()
+        .unwrap()
+        .ctx
+        .library
+        .get("tt0000005")
+        .is_none());
+    let stored: LibBucket =
+        EnvMock::get_storage_sync("recent_library").expect("recent_library is stored");
+    assert_eq!(stored.items.get(&id), Some(&lib_item()));
+}
+
+#[test]
+fn videos_and_seasons_can_be_marked_as_watched() {
+    EnvMock::reset();
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    let videos = (1..=2)
+        .flat_map(|season| {
+            (1..=3).map(move |episode| {
+                json!({
+                    "id": format!("tt0000007:{}:{}", season, episode),
+                    "title": "Episode",
+                    "released": "2019-01-01T00:00:00.000Z",
+                    "season": season,
+                    "episode": episode
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    let meta: Box<MetaDetail> = serde_json::from_value(json!({
+        "id": "tt0000007",
+        "type": "series",
+        "name": "Sample",
+        "videos": videos
+    }))
+    .expect("sample meta must deserialize");
+    let watched = || {
+        let model = runtime.app.read().unwrap();
+        let item = model
+            .ctx
+            .library
+            .get(&meta.id)
+            .expect("item is in the library");
+        item.watched_bitfield(&meta.videos)
+            .watched_videos()
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    user_op(&runtime, ActionUser::AddToLibrary(meta.to_owned()));
+    user_op(
+        &runtime,
+        ActionUser::MarkSeasonAsWatched {
+            meta: meta.to_owned(),
+            season: 1,
+            is_watched: true,
+        },
+    );
+    user_op(
+        &runtime,
+        ActionUser::MarkVideoAsWatched {
+            meta: meta.to_owned(),
+            video_id: "tt0000007:1:2".to_owned(),
+            is_watched: false,
+        },
+    );
+    user_op(
+        &runtime,
+        ActionUser::MarkVideoAsWatched {
+            meta: meta.to_owned(),
+            video_id: "tt0000007:2:1".to_owned(),
+            is_watched: true,
+        },
+    );
+    assert_eq!(
+        watched(),
+        vec!["tt0000007:1:1", "tt0000007:1:3", "tt0000007:2:1"]
+    );
+    let stored: LibBucket =
+        EnvMock::get_storage_sync("recent_library").expect("recent_library is stored");
+    let field = match &stored.items[&meta.id].state.watched {
+        Some(WatchedState::Parsed(field)) => field,
+        x => panic!("watched is not parsed, but instead: {:?}", x),
+    };
+    assert_eq!(field.anchor_video, "tt0000007:2:1");
+    assert_eq!(field.anchor_length, 4);
+}
+
+#[test]
+fn watched_field_without_its_anchor_is_kept() {
+    EnvMock::reset();
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    let meta: Box<MetaDetail> = serde_json::from_value(sample_meta(3)).unwrap();
+    user_op(&runtime, ActionUser::AddToLibrary(meta.to_owned()));
+    user_op(
+        &runtime,
+        ActionUser::MarkVideoAsWatched {
+            meta: meta.to_owned(),
+            video_id: "tt1:1:2".to_owned(),
+            is_watched: true,
+        },
+    );
+    let stored_item = || {
+        let stored: LibBucket =
+            EnvMock::get_storage_sync("recent_library").expect("recent_library is stored");
+        stored.items[&meta.id].to_owned()
+    };
+    let item = stored_item();
+
+    // The add-on no longer has the anchor video, e.g. it changed the video ids
+    let mut changed_meta = meta.to_owned();
+    changed_meta.videos.retain(|video| video.id != "tt1:1:2");
+    user_op(
+        &runtime,
+        ActionUser::MarkVideoAsWatched {
+            meta: changed_meta,
+            video_id: "tt1:1:3".to_owned(),
+            is_watched: true,
+        },
+    );
+    assert_eq!(stored_item(), item, "the watched field is not overwritten");
+    assert_eq!(
+        runtime.app.read().unwrap().ctx.library.get(&meta.id),
+        Some(&item)
+    );
+}
+
+#[test]
+fn removed_unwatched_items_are_pushed() {
+    EnvMock::reset();
+    let item = watched_lib_item("tt0000006");
+    mock_login("auth_key", &[item.to_owned()]);
+    EnvMock::respond_api("datastorePut", &json!({ "success": true }));
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    user_op(
+        &runtime,
+        ActionUser::Login {
+            email: "user@stremio.com".into(),
+            password: "password".into(),
+        },
+    );
+
+    let puts = || {
+        EnvMock::requests()
+            .into_iter()
+            .filter(|r| r.url.ends_with("/api/datastorePut"))
+            .collect::<Vec<_>>()
+    };
+    user_op(&runtime, ActionUser::RewindLibItem(item.id.to_owned()));
+    let pushed = puts();
+    assert_eq!(pushed.len(), 1, "rewound item is pushed");
+    assert_eq!(pushed[0].body["changes"][0]["state"]["timeOffset"], 0);
+    user_op(&runtime, ActionUser::RemoveFromLibrary(item.id.to_owned()));
+    let pushed = puts();
+    assert_eq!(pushed.len(), 2, "removed item without watch time is pushed");
+    assert_eq!(pushed[1].body["changes"][0]["removed"], true);
+    let model = runtime.app.read().unwrap();
+    assert!(
+        model
+            .ctx
+            .library
+            .get(&item.id)
+            .expect("item is kept")
+            .removed
+    );
+}
+
+#[test]
+fn failed_pushes_are_retried_from_the_outbox() {
+    EnvMock::reset();
+    mock_login("auth_key", &[]);
+    // The retries elapse, until the backoff gets longer than a minute
+    EnvMock::skip_delays_up_to(Duration::from_secs(60));
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    user_op(
+        &runtime,
+        ActionUser::Login {
+            email: "user@stremio.com".into(),
+            password: "password".into(),
+        },
+    );
+    // There's no response for datastorePut, so pushing fails
+    user_op(
+        &runtime,
+        ActionUser::AddToLibrary(sample_movie("tt0000008")),
+    );
+
+    let puts = || {
+        EnvMock::request_urls()
+            .into_iter()
+            .filter(|url| url.ends_with("/api/datastorePut"))
+            .count()
+    };
+    assert_eq!(puts(), 5, "pushed once and retried after 5, 10, 20 and 40s");
+    let outbox: LibBucket =
+        EnvMock::get_storage_sync("library_outbox").expect("library_outbox is stored");
+    assert!(outbox.items.contains_key("tt0000008"));
+
+    // After a restart, the library is synced and the outbox is pushed
+    EnvMock::respond_api("datastorePut", &json!({ "success": true }));
+    let before_restart = EnvMock::requests().len();
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    let urls = EnvMock::request_urls().split_off(before_restart);
+    assert!(urls.contains(&"https://api.strem.io/api/datastoreMeta".to_owned()));
+    assert!(urls.contains(&"https://api.strem.io/api/datastorePut".to_owned()));
+    let outbox: LibBucket =
+        EnvMock::get_storage_sync("library_outbox").expect("library_outbox is stored");
+    assert!(outbox.items.is_empty(), "outbox is empty");
+    assert!(runtime
+        .app
+        .read()
+        .unwrap()
+        .ctx
+        .lib_sync
+        .outbox
+        .items
+        .is_empty());
+}
+
+#[test]
+fn outbox_of_another_user_is_dropped() {
+    EnvMock::reset();
+    let item = watched_lib_item("tt0000009");
+    let outbox = json!({ "uid": "other_user_id", "items": { item.id.to_owned(): item } });
+    EnvMock::set_storage("library_outbox", Some(&outbox))
+        .wait()
+        .expect("library_outbox is stored");
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+
+    let model = runtime.app.read().unwrap();
+    assert!(
+        model.ctx.lib_sync.outbox.items.is_empty(),
+        "outbox is empty"
+    );
+    assert!(EnvMock::requests().is_empty(), "nothing was pushed");
+}
+
+#[test]
+fn old_settings_are_migrated() {
+    EnvMock::reset();
+    let stored = json!({
+        "auth": null,
+        "addons": [],
+        "settings": {
+            "language": "eng",
+            "subtitles_size": "125%",
+            "subtitles_language": "bul",
+            "subtitles_background": "",
+            "subtitles_color": "#fff",
+            "subtitles_outline_color": "#000",
+            "autoplay_next_vid": "true",
+            "server_url": "http://127.0.0.1:11470/",
+            "use_external_player": "false",
+            "pause_on_lost_focus": "not a bool",
+            "show_vid_overview": "false"
+        }
+    });
+    EnvMock::set_storage("userData", Some(&stored))
+        .wait()
+        .expect("userData is stored");
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+
+    let model = runtime.app.read().unwrap();
+    let settings = &model.ctx.content.settings;
+    assert_eq!(settings.subtitles_size, Percentage(125));
+    assert_eq!(settings.subtitles_language.code(), "bul");
+    assert_eq!(settings.subtitles_background, None);
+    assert!(settings.autoplay_next_vid);
+    // Values which can't be migrated fall back to the defaults
+    assert!(!settings.pause_on_lost_focus);
+    // The migrated settings are written back
+    let stored: serde_json::Value = EnvMock::get_storage_sync("userData").unwrap();
+    assert_eq!(stored["settings"]["subtitles_size"], 125);
+    assert_eq!(stored["settings"]["autoplay_next_vid"], true);
+}
+
+#[test]
+fn missing_settings_fall_back_to_defaults() {
+    EnvMock::reset();
+    let stored = json!({
+        "auth": null,
+        "addons": [],
+        "settings": { "autoplay_next_vid": true }
+    });
+    EnvMock::set_storage("userData", Some(&stored))
+        .wait()
+        .expect("userData is stored");
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+
+    let model = runtime.app.read().unwrap();
+    assert_eq!(
+        model.ctx.content.settings,
+        Settings {
+            autoplay_next_vid: true,
+            ..Settings::default()
+        }
+    );
+}
+
+#[test]
+fn invalid_stored_settings_fall_back_per_field() {
+    EnvMock::reset();
+    let stored = json!({
+        "auth": null,
+        "addons": [],
+        "settings": {
+            "subtitles_size": 5,
+            "subtitles_color": "white",
+            "server_url": "ftp://127.0.0.1/",
+            "autoplay_next_vid": true,
+            "show_vid_overview": "not a bool"
+        }
+    });
+    EnvMock::set_storage("userData", Some(&stored))
+        .wait()
+        .expect("userData is stored");
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+
+    let model = runtime.app.read().unwrap();
+    assert!(model.ctx.content.addons.is_empty(), "addons are kept");
+    assert_eq!(
+        model.ctx.content.settings,
+        Settings {
+            autoplay_next_vid: true,
+            ..Settings::default()
+        }
+    );
+}
+
+#[test]
+fn invalid_settings_are_not_stored() {
+    EnvMock::reset();
+    let (runtime, rx) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    let settings = Settings {
+        subtitles_color: Color("white".into()),
+        ..Settings::default()
+    };
+    run(runtime.dispatch(&Action::Settings(ActionSettings::Store(Box::new(settings))).into()));
+
+    assert_eq!(
+        runtime.app.read().unwrap().ctx.content.settings,
+        Settings::default()
+    );
+    assert_eq!(
+        EnvMock::get_storage_sync::<CtxContent>("userData"),
+        None,
+        "nothing is persisted"
+    );
+    // Dropping the runtime ends the stream of events
+    drop(runtime);
+    let events: Vec<String> = rx
+        .wait()
+        .filter_map(Result::ok)
+        .filter_map(|msg| match msg {
+            RuntimeEv::Event(Event::SettingsInvalid(e)) => Some(e.to_string()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(events, vec!["invalid subtitles colour: white"]);
+}
+
+fn addon_manifest(version: &str, resources: serde_json::Value) -> serde_json::Value {
+    json!({
+        "id": "org.test",
+        "version": version,
+        "name": "Test",
+        "types": ["movie"],
+        "resources": resources
+    })
+}
+
+fn install_events(rx: futures::sync::mpsc::Receiver<RuntimeEv>) -> Vec<String> {
+    rx.wait()
+        .filter_map(Result::ok)
+        .filter_map(|msg| match msg {
+            RuntimeEv::Event(Event::AddonInstalled(url)) => Some(format!("installed {}", url)),
+            RuntimeEv::Event(Event::AddonUpgraded(url, from, to)) => {
+                Some(format!("upgraded {} from {} to {}", url, from, to))
+            }
+            RuntimeEv::Event(Event::AddonInstallFailed(url, e)) => Some(match e {
+                AddonManifestError::ManifestInvalid(issues) => format!(
+                    "invalid {}: {}",
+                    url,
+                    issues.iter().map(ToString::to_string).join(", ")
+                ),
+                AddonManifestError::ManifestFetch(_) => format!("not fetched {}", url),
+                AddonManifestError::ReplacesProtected => format!("protected {}", url),
+                AddonManifestError::OlderVersion(installed) => {
+                    format!("older {} than {}", url, installed)
+                }
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn install_refuses_invalid_manifests() {
+    EnvMock::reset();
+    let (runtime, rx) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    let descriptor = |resources: serde_json::Value| -> Descriptor {
+        serde_json::from_value(json!({
+            "manifest": addon_manifest("1.0.0", resources),
+            "transportUrl": "https://addon.test/manifest.json"
+        }))
+        .expect("descriptor must deserialize")
+    };
+    let addons_len = runtime.app.read().unwrap().ctx.content.addons.len();
+    run(runtime
+        .dispatch(&Action::AddonOp(ActionAddon::Install(Box::new(descriptor(json!([]))))).into()));
+    assert_eq!(
+        runtime.app.read().unwrap().ctx.content.addons.len(),
+        addons_len,
+        "not installed"
+    );
+    run(runtime.dispatch(
+        &Action::AddonOp(ActionAddon::Install(Box::new(descriptor(json!([
+            "stream"
+        ])))))
+        .into(),
+    ));
+    assert_eq!(
+        runtime.app.read().unwrap().ctx.content.addons.len(),
+        addons_len + 1,
+        "installed"
+    );
+
+    drop(runtime);
+    assert_eq!(
+        install_events(rx),
+        vec![
+            "invalid https://addon.test/manifest.json: resources is empty",
+            "installed https://addon.test/manifest.json",
+        ]
+    );
+}
+
+#[test]
+fn install_from_url_dedupes_and_upgrades() {
+    EnvMock::reset();
+    let (runtime, rx) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    let addons_len = runtime.app.read().unwrap().ctx.content.addons.len();
+    let install = |url: &str| {
+        run(runtime.dispatch(&Action::AddonOp(ActionAddon::InstallFromUrl(url.into())).into()));
+    };
+    let installed = || {
+        let model = runtime.app.read().unwrap();
+        let addons = &model.ctx.content.addons;
+        assert_eq!(addons.len(), addons_len + 1, "installed exactly once");
+        let addon = addons.last().unwrap();
+        (
+            addon.transport_url.to_owned(),
+            addon.manifest.version.to_string(),
+        )
+    };
+
+    let url = "https://addon.test/manifest.json";
+    EnvMock::respond("GET", url, &addon_manifest("1.0.0", json!(["stream"])));
+    install(url);
+    assert_eq!(installed(), (url.to_owned(), "1.0.0".to_owned()));
+    install(url);
+    assert_eq!(installed(), (url.to_owned(), "1.0.0".to_owned()));
+    EnvMock::respond("GET", url, &addon_manifest("1.1.0", json!(["stream"])));
+    install(url);
+    assert_eq!(installed(), (url.to_owned(), "1.1.0".to_owned()));
+    // The same add-on from a different URL replaces it, without its flags
+    {
+        let mut model = runtime.app.write().unwrap();
+        model.ctx.content.addons.last_mut().unwrap().flags.disabled = true;
+    }
+    let mirror_url = "https://mirror.addon.test/manifest.json";
+    EnvMock::respond(
+        "GET",
+        mirror_url,
+        &addon_manifest("1.1.0", json!(["stream"])),
+    );
+    install(mirror_url);
+    assert_eq!(installed(), (mirror_url.to_owned(), "1.1.0".to_owned()));
+    {
+        let model = runtime.app.read().unwrap();
+        let addon = model.ctx.content.addons.last().unwrap();
+        assert!(!addon.flags.disabled, "flags are not carried over");
+    }
+    // The same URL serving another id replaces it as well
+    let mut renamed = addon_manifest("1.0.0", json!(["stream"]));
+    renamed["id"] = json!("org.test.renamed");
+    EnvMock::respond("GET", mirror_url, &renamed);
+    install(mirror_url);
+    assert_eq!(installed(), (mirror_url.to_owned(), "1.0.0".to_owned()));
+    install("https://unknown.test/manifest.json");
+    assert_eq!(installed(), (mirror_url.to_owned(), "1.0.0".to_owned()));
+    let stored: CtxContent = EnvMock::get_storage_sync("userData").expect("userData is stored");
+    assert_eq!(stored, runtime.app.read().unwrap().ctx.content);
+
+    drop(runtime);
+    assert_eq!(
+        install_events(rx),
+        vec![
+            "installed https://addon.test/manifest.json",
+            "installed https://addon.test/manifest.json",
+            "upgraded https://addon.test/manifest.json from 1.0.0 to 1.1.0",
+            "installed https://mirror.addon.test/manifest.json",
+            "installed https://mirror.addon.test/manifest.json",
+            "not fetched https://unknown.test/manifest.json",
+        ]
+    );
+}
+
+#[test]
+fn install_refuses_older_versions_and_replacing_protected() {
+    EnvMock::reset();
+    let (runtime, rx) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    let url = "https://addon.test/manifest.json";
+    let mirror_url = "https://mirror.addon.test/manifest.json";
+    runtime.app.write().unwrap().ctx.content.addons = vec![serde_json::from_value(json!({
+        "manifest": addon_manifest("1.1.0", json!(["stream"])),
+        "transportUrl": url,
+        "flags": { "protected": true }
+    }))
+    .expect("descriptor must deserialize")];
+    let install = |url: &str, version: &str| {
+        EnvMock::respond("GET", url, &addon_manifest(version, json!(["stream"])));
+        run(runtime.dispatch(&Action::AddonOp(ActionAddon::InstallFromUrl(url.into())).into()));
+    };
+
+    install(mirror_url, "1.2.0");
+    install(url, "1.0.0");
+    install(url, "1.2.0");
+    {
+        let model = runtime.app.read().unwrap();
+        let addons = &model.ctx.content.addons;
+        assert_eq!(addons.len(), 1);
+        assert_eq!(addons[0].transport_url, url);
+        assert_eq!(addons[0].manifest.version.to_string(), "1.2.0");
+        assert!(addons[0].flags.protected, "flags are kept");
+    }
+
+    drop(runtime);
+    assert_eq!(
+        install_events(rx),
+        vec![
+            "protected https://mirror.addon.test/manifest.json",
+            "older https://addon.test/manifest.json than 1.1.0",
+            "upgraded https://addon.test/manifest.json from 1.1.0 to 1.2.0",
+        ]
+    );
+}
+
+#[test]
+fn refresh_manifests_upgrades_and_pushes() {
+    EnvMock::reset();
+    mock_login("auth_key", &[]);
+    let upgraded_url = "https://addon.test/manifest.json";
+    let failing_url = "https://failing.addon.test/manifest.json";
+    let other_url = "https://other.addon.test/manifest.json";
+    let current_url = "https://current.addon.test/manifest.json";
+    let manifest = |id: &str, version: &str| {
+        let mut manifest = addon_manifest(version, json!(["stream"]));
+        manifest["id"] = json!(id);
+        manifest
+    };
+    EnvMock::respond_api(
+        "addonCollectionGet",
+        &json!({
+            "addons": [
+                {
+                    "manifest": addon_manifest("1.0.0", json!(["stream"])),
+                    "transportUrl": upgraded_url,
+                    "flags": { "protected": true }
+                },
+                { "manifest": manifest("org.failing", "1.0.0"), "transportUrl": failing_url },
+                { "manifest": manifest("org.other", "1.0.0"), "transportUrl": other_url },
+                { "manifest": manifest("org.current", "1.0.0"), "transportUrl": current_url }
+            ],
+            "lastModified": "2019-01-01T00:00:00.000Z"
+        }),
+    );
+    EnvMock::respond_api("addonCollectionSet", &json!({ "success": true }));
+    EnvMock::respond(
+        "GET",
+        upgraded_url,
+        &addon_manifest("1.1.0", json!(["stream", "meta"])),
+    );
+    EnvMock::respond("GET", other_url, &manifest("org.other", "2.0.0"));
+    EnvMock::respond("GET", current_url, &manifest("org.current", "1.0.0"));
+    let (runtime, rx) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    run(runtime.dispatch(
+        &Action::UserOp(ActionUser::Login {
+            email: "user@stremio.com".into(),
+            password: "password".into(),
+        })
+        .into(),
+    ));
+    run(runtime.dispatch(&Action::AddonOp(ActionAddon::RefreshManifests).into()));
+
+    {
+        let model = runtime.app.read().unwrap();
+        let addons = &model.ctx.content.addons;
+        assert_eq!(addons[0].manifest.version.to_string(), "1.1.0");
+        assert_eq!(addons[0].manifest.resources.len(), 2);
+        assert!(addons[0].flags.protected, "flags are preserved");
+        assert_eq!(addons[1].manifest.version.to_string(), "1.0.0");
+        let set_req = EnvMock::requests()
+            .into_iter()
+            .find(|r| r.url.ends_with("/api/addonCollectionSet"))
+            .expect("addons were pushed");
+        assert_eq!(set_req.body["addons"][0]["manifest"]["version"], "1.1.0");
+    }
+
+    drop(runtime);
+    let events: Vec<String> = rx
+        .wait()
+        .filter_map(Result::ok)
+        .filter_map(|msg| match msg {
+            RuntimeEv::Event(Event::AddonUpgraded(url, from, to)) => {
+                Some(format!("upgraded {} from {} to {}", url, from, to))
+            }
+            RuntimeEv::Event(Event::AddonUpToDate(url)) => Some(format!("up to date {}", url)),
+            RuntimeEv::Event(Event::AddonRefreshFailed(url, _)) => {
+                Some(format!("not refreshed {}", url))
+            }
+            RuntimeEv::Event(Event::CtxAddonsPushed) => Some("pushed".to_owned()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            "upgraded https://addon.test/manifest.json from 1.0.0 to 1.1.0",
+            "not refreshed https://failing.addon.test/manifest.json",
+            "upgraded https://other.addon.test/manifest.json from 1.0.0 to 2.0.0",
+            "up to date https://current.addon.test/manifest.json",
+            "pushed",
+        ],
+        "the upgrades are pushed once"
+    );
+}
+
+#[test]
+fn manifests_are_refreshed_periodically() {
+    EnvMock::reset();
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    let url = "https://addon.test/manifest.json";
+    let descriptor = sample_addon("addon.test", addon_manifest("1.0.0", json!(["stream"])));
+    run(runtime.dispatch(&Action::AddonOp(ActionAddon::Install(Box::new(descriptor))).into()));
+    EnvMock::respond("GET", url, &addon_manifest("1.1.0", json!(["stream"])));
+
+    // Scheduled once the ctx is loaded; delays don't elapse, so the timer is triggered here
+    run(runtime.dispatch(&Internal::AddonsRefreshScheduled.into()));
+    assert!(EnvMock::request_urls().contains(&url.to_owned()));
+    let model = runtime.app.read().unwrap();
+    let addon = model
+        .ctx
+        .content
+        .addons
+        .iter()
+        .find(|addon| addon.transport_url == url)
+        .unwrap();
+    assert_eq!(addon.manifest.version.to_string(), "1.1.0");
+}
+
+#[test]
+fn addons_can_be_moved_disabled_and_removed() {
+    EnvMock::reset();
+    mock_login("auth_key", &[]);
+    EnvMock::respond_api("addonCollectionSet", &json!({ "success": true }));
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    run(runtime.dispatch(
+        &Action::UserOp(ActionUser::Login {
+            email: "user@stremio.com".into(),
+            password: "password".into(),
+        })
+        .into(),
+    ));
+    let requests_before = EnvMock::request_urls().len();
+    let pushes = || {
+        EnvMock::request_urls()
+            .split_off(requests_before)
+            .iter()
+            .filter(|url| url.ends_with("/api/addonCollectionSet"))
+            .count()
+    };
+    let dispatch = |action: ActionAddon| run(runtime.dispatch(&Action::AddonOp(action).into()));
+    let url = |id: &str| format!("https://{}.addon.test/manifest.json", id);
+    for id in &["first", "second"] {
+        let mut manifest = addon_manifest("1.0.0", json!(["stream"]));
+        manifest["id"] = json!(id);
+        let descriptor = sample_addon(&format!("{}.addon.test", id), manifest);
+        dispatch(ActionAddon::Install(Box::new(descriptor)));
+    }
+    let stream_addons = || {
+        let model = runtime.app.read().unwrap();
+        let path = ResourceRef::without_extra("stream", "movie", "tt0000001");
+        AggrRequest::AllOfResource(path)
+            .plan(&model.ctx.content.addons)
+            .into_iter()
+            .map(|(addon, _)| addon.manifest.id.to_owned())
+            .filter(|id| id == "first" || id == "second")
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(stream_addons(), vec!["first", "second"]);
+
+    dispatch(ActionAddon::MoveUp {
+        transport_url: url("second"),
+    });
+    assert_eq!(stream_addons(), vec!["second", "first"]);
+    // The last one can't be moved down
+    let addons_len = runtime.app.read().unwrap().ctx.content.addons.len();
+    dispatch(ActionAddon::MoveDown {
+        transport_url: url("first"),
+    });
+    assert_eq!(
+        runtime.app.read().unwrap().ctx.content.addons[addons_len - 1].transport_url,
+        url("first")
+    );
+
+    dispatch(ActionAddon::Disable {
+        transport_url: url("second"),
+    });
+    assert_eq!(stream_addons(), vec!["first"]);
+    let stored: CtxContent = EnvMock::get_storage_sync("userData").expect("userData is stored");
+    assert_eq!(stored, runtime.app.read().unwrap().ctx.content);
+    dispatch(ActionAddon::Enable {
+        transport_url: url("second"),
+    });
+    assert_eq!(stream_addons(), vec!["second", "first"]);
+    dispatch(ActionAddon::Remove {
+        transport_url: url("second"),
+    });
+    assert_eq!(stream_addons(), vec!["first"]);
+    assert_eq!(pushes(), 6, "every change but the no-op is pushed");
+}
+
+#[test]
+fn anon_pull_upgrades_addons_and_keeps_the_flags() {
+    EnvMock::reset();
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    let zero_ver = semver::Version::new(0, 0, 0);
+    {
+        let addons = &mut runtime.app.write().unwrap().ctx.content.addons;
+        addons[0].manifest.version = zero_ver.clone();
+        addons[0].flags.extra.insert("foo".into(), "bar".into());
+    }
+    run(runtime.dispatch(&Action::UserOp(ActionUser::PullAndUpdateAddons).into()));
+
+    assert!(EnvMock::requests().is_empty(), "upgraded locally");
+    let model = runtime.app.read().unwrap();
+    let first_addon = &model.ctx.content.addons[0];
+    assert_ne!(first_addon.manifest.version, zero_ver, "upgraded");
+    assert_eq!(first_addon.flags.extra.get("foo"), Some(&json!("bar")));
+}
@@ -0,0 +1,125 @@
+use super::*;
+use crate::state_types::*;
+use crate::types::MetaDetail;
+use serde_json::json;
+use stremio_derive::Model;
+use tokio::runtime::current_thread::run;
+
+#[derive(Model, Debug, Default)]
+struct Model {
+    ctx: Ctx<EnvMock>,
+    detail: Detail,
+}
+
+fn load_detail(runtime: &Runtime<EnvMock, Model>, video_id: Option<&str>) {
+    run(runtime.dispatch(
+        &Action::Load(ActionLoad::Detail {
+            type_name: "series".into(),
+            id: "tt1".into(),
+            video_id: video_id.map(String::from),
+        })
+        .into(),
+    ));
+}
+
+fn runtime_with_addons() -> Runtime<EnvMock, Model> {
+    EnvMock::reset();
+    EnvMock::respond(
+        "GET",
+        "https://one.example.com/meta/series/tt1.json",
+        &json!({ "meta": sample_meta(3), "cacheMaxAge": 3600 }),
+    );
+    // The second add-on has no meta for it
+    EnvMock::respond(
+        "GET",
+        "https://one.example.com/stream/series/tt1:1:1.json",
+        &json!({ "streams": [{ "url": "https://example.com/1.mp4" }], "cacheMaxAge": 3600 }),
+    );
+    EnvMock::respond(
+        "GET",
+        "https://two.example.com/stream/series/tt1:1:1.json",
+        &json!({ "streams": [] }),
+    );
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    runtime.app.write().unwrap().ctx.content.addons = vec![
+        sample_series_addon("one.example.com"),
+        sample_series_addon("two.example.com"),
+    ];
+    runtime
+}
+
+#[test]
+fn metas_and_streams_are_loaded() {
+    let runtime = runtime_with_addons();
+    load_detail(&runtime, None);
+    {
+        let model = runtime.app.read().unwrap();
+        let detail = &model.detail;
+        assert_eq!(detail.metas.len(), 2, "a group for each add-on");
+        match &detail.metas[0].content {
+            Loadable::Ready(meta) => assert_eq!(meta.videos.len(), 3),
+            x => panic!("meta is not Ready, but instead: {:?}", x),
+        }
+        assert!(matches!(detail.metas[1].content, Loadable::Err(_)));
+        assert!(detail.streams.is_empty(), "no video is selected");
+        assert_eq!(detail.lib_item, None);
+    }
+
+    load_detail(&runtime, Some("tt1:1:1"));
+    let model = runtime.app.read().unwrap();
+    let streams = model
+        .detail
+        .streams
+        .iter()
+        .map(|group| match &group.content {
+            Loadable::Ready(streams) => streams.len(),
+            x => panic!("streams are not Ready, but instead: {:?}", x),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(streams, vec![1, 0]);
+    assert_eq!(
+        model.detail.selected.as_ref().unwrap().video_id,
+        Some("tt1:1:1".to_owned())
+    );
+}
+
+#[test]
+fn lib_item_and_watched_videos_follow_the_library() {
+    let runtime = runtime_with_addons();
+    load_detail(&runtime, None);
+    assert!(runtime.app.read().unwrap().detail.watched_videos.is_empty());
+
+    let meta: Box<MetaDetail> = serde_json::from_value(sample_meta(3)).unwrap();
+    run(runtime.dispatch(&Action::UserOp(ActionUser::AddToLibrary(meta.to_owned())).into()));
+    run(runtime.dispatch(
+        &Action::UserOp(ActionUser::MarkVideoAsWatched {
+            meta,
+            video_id: "tt1:1:2".into(),
+            is_watched: true,
+        })
+        .into(),
+    ));
+    let model = runtime.app.read().unwrap();
+    let lib_item = model
+        .detail
+        .lib_item
+        .as_ref()
+        .expect("item is in the library");
+    assert_eq!(Some(lib_item), model.ctx.library.get("tt1"));
+    assert_eq!(model.detail.watched_videos, vec!["tt1:1:2".to_owned()]);
+}
+
+#[test]
+fn cached_responses_are_used() {
+    let runtime = runtime_with_addons();
+    load_detail(&runtime, Some("tt1:1:1"));
+    load_detail(&runtime, Some("tt1:1:1"));
+    let urls = EnvMock::request_urls();
+    let count = |url: &str| urls.iter().filter(|u| *u == url).count();
+    assert_eq!(count("https://one.example.com/meta/series/tt1.json"), 1);
+    assert_eq!(
+        count("https://one.example.com/stream/series/tt1:1:1.json"),
+        1
+    );
+}
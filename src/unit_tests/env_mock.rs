@@ -0,0 +1,167 @@
+use crate::state_types::*;
+use chrono::{DateTime, TimeZone, Utc};
+use futures::{future, Future};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use tokio::executor::current_thread::spawn;
+
+// Every test runs in its own thread, and the futures are executed by a current_thread runtime,
+// so keeping the state thread local isolates the tests from one another
+thread_local! {
+    // None stands for a request that never gets a response
+    static RESPONSES: RefCell<HashMap<(String, String), Option<serde_json::Value>>> = Default::default();
+    static REQUESTS: RefCell<Vec<RequestRecord>> = Default::default();
+    static STORAGE: RefCell<BTreeMap<String, String>> = Default::default();
+    static NOW: RefCell<DateTime<Utc>> = RefCell::new(Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap());
+    // Delays up to that long elapse right away, longer ones never do
+    static SKIP_DELAYS: RefCell<Option<Duration>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestRecord {
+    pub method: String,
+    pub url: String,
+    pub body: serde_json::Value,
+}
+
+// Test environment: serves canned responses and records all requests that were made
+pub enum EnvMock {}
+impl EnvMock {
+    pub fn reset() {
+        RESPONSES.with(|r| r.borrow_mut().clear());
+        REQUESTS.with(|r| r.borrow_mut().clear());
+        STORAGE.with(|s| s.borrow_mut().clear());
+        NOW.with(|n| *n.borrow_mut() = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap());
+        SKIP_DELAYS.with(|d| *d.borrow_mut() = None);
+    }
+    pub fn set_now(now: DateTime<Utc>) {
+        NOW.with(|n| *n.borrow_mut() = now);
+    }
+    pub fn respond<T: Serialize>(method: &str, url: &str, body: &T) {
+        let body = serde_json::to_value(body).expect("mock response must serialize");
+        RESPONSES.with(|r| {
+            r.borrow_mut()
+                .insert((method.to_owned(), url.to_owned()), Some(body))
+        });
+    }
+    pub fn respond_never(method: &str, url: &str) {
+        RESPONSES.with(|r| {
+            r.borrow_mut()
+                .insert((method.to_owned(), url.to_owned()), None)
+        });
+    }
+    // By default, delays never elapse; when skipped, they elapse right away
+    pub fn skip_delays(skip: bool) {
+        let max = if skip {
+            Some(Duration::from_secs(u64::MAX))
+        } else {
+            None
+        };
+        SKIP_DELAYS.with(|d| *d.borrow_mut() = max);
+    }
+    // Only the delays up to that long are skipped (e.g. retries, but not periodic jobs)
+    pub fn skip_delays_up_to(max: Duration) {
+        SKIP_DELAYS.with(|d| *d.borrow_mut() = Some(max));
+    }
+    fn is_delay_skipped(duration: Duration) -> bool {
+        SKIP_DELAYS.with(|d| d.borrow().is_some_and(|max| duration <= max))
+    }
+    // Wraps the result the same way the API does
+    pub fn respond_api<T: Serialize>(method_name: &str, result: &T) {
+        let url = format!("{}/api/{}", Self::api_url(), method_name);
+        Self::respond("POST", &url, &serde_json::json!({ "result": result }));
+    }
+    pub fn requests() -> Vec<RequestRecord> {
+        REQUESTS.with(|r| r.borrow().to_owned())
+    }
+    pub fn request_urls() -> Vec<String> {
+        Self::requests().into_iter().map(|r| r.url).collect()
+    }
+    pub fn get_storage_sync<T: DeserializeOwned>(key: &str) -> Option<T> {
+        STORAGE.with(|s| {
+            s.borrow()
+                .get(key)
+                .map(|v| serde_json::from_str(v).expect("stored value must deserialize"))
+        })
+    }
+}
+impl Environment for EnvMock {
+    fn fetch_serde<IN, OUT>(in_req: Request<IN>) -> EnvFuture<OUT>
+    where
+        IN: 'static + Serialize,
+        OUT: 'static + DeserializeOwned,
+    {
+        let (parts, body) = in_req.into_parts();
+        let record = RequestRecord {
+            method: parts.method.as_str().to_owned(),
+            url: parts.uri.to_string(),
+            body: serde_json::to_value(&body).expect("request body must serialize"),
+        };
+        let resp = RESPONSES.with(|r| {
+            r.borrow()
+                .get(&(record.method.to_owned(), record.url.to_owned()))
+                .cloned()
+        });
+        let result = match resp {
+            // Going through a string, like the real environments do, since some types
+            // (e.g. the info hash) can't be deserialized from a serde_json::Value
+            Some(Some(resp)) => serde_json::from_str(&resp.to_string()).map_err(Into::into),
+            Some(None) => {
+                REQUESTS.with(|r| r.borrow_mut().push(record));
+                return Box::new(future::empty());
+            }
+            None => Err(format!("no mock response for {} {}", record.method, record.url).into()),
+        };
+        REQUESTS.with(|r| r.borrow_mut().push(record));
+        Box::new(future::result(result))
+    }
+    fn exec(fut: Box<dyn Future<Item = (), Error = ()>>) {
+        spawn(fut);
+    }
+    fn get_storage<T: 'static + DeserializeOwned>(key: &str) -> EnvFuture<Option<T>> {
+        let result = STORAGE.with(|s| match s.borrow().get(key) {
+            Some(v) => serde_json::from_str(v).map(Some).map_err(Into::into),
+            None => Ok(None),
+        });
+        Box::new(future::result(result))
+    }
+    fn set_storage<T: Serialize>(key: &str, value: Option<&T>) -> EnvFuture<()> {
+        let result = STORAGE.with(|s| {
+            let mut storage = s.borrow_mut();
+            match value {
+                Some(v) => serde_json::to_string(v).map(|v| {
+                    storage.insert(key.to_owned(), v);
+                }),
+                None => {
+                    storage.remove(key);
+                    Ok(())
+                }
+            }
+        });
+        Box::new(future::result(result.map_err(Into::into)))
+    }
+    fn now() -> DateTime<Utc> {
+        NOW.with(|n| *n.borrow())
+    }
+    // The runtime waits for all the futures, so delays which never elapse fail instead;
+    // otherwise, a timer would keep the test running forever
+    fn delay(duration: Duration) -> EnvFuture<()> {
+        if Self::is_delay_skipped(duration) {
+            Box::new(future::ok(()))
+        } else {
+            Box::new(future::err("the delay never elapses".into()))
+        }
+    }
+    // A timeout which never elapses is the same as no timeout at all
+    fn timeout<T: 'static>(ft: EnvFuture<T>, duration: Duration) -> EnvFuture<T> {
+        if Self::is_delay_skipped(duration) {
+            let timeout = future::err::<T, EnvError>(TimeoutError.into());
+            Box::new(ft.select(timeout).map(|(x, _)| x).map_err(|(e, _)| e))
+        } else {
+            ft
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use super::*;
+use crate::state_types::*;
+use crate::types::LibItem;
+use serde_json::json;
+use stremio_derive::Model;
+use tokio::runtime::current_thread::run;
+
+#[derive(Model, Debug, Default)]
+struct Model {
+    ctx: Ctx<EnvMock>,
+    library: LibraryFiltered,
+}
+
+struct Sample<'a> {
+    id: &'a str,
+    name: &'a str,
+    type_name: &'a str,
+    ctime: &'a str,
+    last_watched: &'a str,
+    times_watched: u32,
+}
+
+fn lib_item(sample: &Sample, removed: bool, temp: bool) -> LibItem {
+    serde_json::from_value(json!({
+        "_id": sample.id,
+        "removed": removed,
+        "temp": temp,
+        "_ctime": sample.ctime,
+        "_mtime": "2019-06-01T00:00:00.000Z",
+        "state": {
+            "lastWatched": sample.last_watched,
+            "timeWatched": 0,
+            "timeOffset": 0,
+            "overallTimeWatched": 0,
+            "timesWatched": sample.times_watched,
+            "flaggedWatched": 0,
+            "duration": 0,
+            "video_id": "",
+            "watched": "",
+            "noNotif": false
+        },
+        "name": sample.name,
+        "type": sample.type_name,
+        "poster": ""
+    }))
+    .expect("lib item must deserialize")
+}
+
+fn lib_update(runtime: &Runtime<EnvMock, Model>, item: LibItem) {
+    run(runtime.dispatch(&Action::UserOp(ActionUser::LibUpdate(item)).into()));
+}
+
+fn load(runtime: &Runtime<EnvMock, Model>, type_name: Option<&str>, sort: LibrarySort) {
+    let req = LibraryRequest {
+        type_name: type_name.map(ToOwned::to_owned),
+        sort,
+        page: 0,
+    };
+    run(runtime.dispatch(&Action::Load(ActionLoad::LibraryFiltered(req)).into()));
+}
+
+fn item_ids(runtime: &Runtime<EnvMock, Model>) -> Vec<String> {
+    let model = runtime.app.read().unwrap();
+    model
+        .library
+        .items
+        .iter()
+        .map(|i| i.id.to_owned())
+        .collect()
+}
+
+#[test]
+fn filters_and_sorts() {
+    EnvMock::reset();
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    let samples = [
+        Sample {
+            id: "a",
+            name: "Beta",
+            type_name: "movie",
+            ctime: "2019-01-01T00:00:00.000Z",
+            last_watched: "2019-03-01T00:00:00.000Z",
+            times_watched: 1,
+        },
+        Sample {
+            id: "b",
+            name: "alpha",
+            type_name: "movie",
+            ctime: "2019-02-01T00:00:00.000Z",
+            last_watched: "",
+            times_watched: 5,
+        },
+        Sample {
+            id: "c",
+            name: "Gamma",
+            type_name: "series",
+            ctime: "2019-03-01T00:00:00.000Z",
+            last_watched: "2019-02-01T00:00:00.000Z",
+            times_watched: 0,
+        },
+    ];
+    load(&runtime, None, LibrarySort::LastWatched);
+    for sample in &samples {
+        lib_update(&runtime, lib_item(sample, false, false));
+    }
+    let mut hidden = lib_item(&samples[0], true, false);
+    hidden.id = "removed".into();
+    lib_update(&runtime, hidden.to_owned());
+    hidden.id = "temp".into();
+    hidden.removed = false;
+    hidden.temp = true;
+    lib_update(&runtime, hidden);
+
+    // Already loaded, so it's updated with the library
+    assert_eq!(item_ids(&runtime), vec!["a", "c", "b"]);
+    {
+        let model = runtime.app.read().unwrap();
+        let types: Vec<(&str, bool)> = model
+            .library
+            .types
+            .iter()
+            .map(|t| (t.type_name.as_str(), t.is_selected))
+            .collect();
+        assert_eq!(types, vec![("movie", false), ("series", false)]);
+        assert_eq!(model.library.load_next, None);
+        assert_eq!(model.library.load_prev, None);
+    }
+    load(&runtime, None, LibrarySort::Name);
+    assert_eq!(item_ids(&runtime), vec!["b", "a", "c"]);
+    load(&runtime, None, LibrarySort::DateAdded);
+    assert_eq!(item_ids(&runtime), vec!["c", "b", "a"]);
+    load(&runtime, Some("movie"), LibrarySort::TimesWatched);
+    assert_eq!(item_ids(&runtime), vec!["b", "a"]);
+    assert!(runtime.app.read().unwrap().library.types[0].is_selected);
+}
+
+#[test]
+fn paginates() {
+    EnvMock::reset();
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    for idx in 0..150 {
+        let id = format!("{:03}", idx);
+        let sample = Sample {
+            id: &id,
+            name: &id,
+            type_name: "channel",
+            ctime: "2019-01-01T00:00:00.000Z",
+            last_watched: "",
+            times_watched: 0,
+        };
+        lib_update(&runtime, lib_item(&sample, false, false));
+    }
+    load(&runtime, None, LibrarySort::Name);
+    let load_next = {
+        let model = runtime.app.read().unwrap();
+        assert_eq!(model.library.items.len(), 100);
+        assert_eq!(model.library.load_prev, None);
+        model
+            .library
+            .load_next
+            .to_owned()
+            .expect("there's a next page")
+    };
+    run(runtime.dispatch(&Action::Load(ActionLoad::LibraryFiltered(load_next)).into()));
+    let model = runtime.app.read().unwrap();
+    assert_eq!(model.library.items.len(), 50);
+    assert_eq!(model.library.items[0].id, "100");
+    assert_eq!(model.library.load_next, None);
+    assert_eq!(model.library.load_prev.as_ref().map(|r| r.page), Some(0));
+}
@@ -0,0 +1,104 @@
+// Offline tests of the models, using the EnvMock environment
+use crate::types::addons::Descriptor;
+use crate::types::LibItem;
+use serde_json::{json, Value};
+
+mod env_mock;
+pub use env_mock::*;
+
+mod ctx;
+
+mod catalog_filtered;
+
+mod catalog_grouped;
+
+mod addons_discovery;
+
+mod notifications;
+
+mod addon_cache;
+
+mod streams;
+
+mod subtitles;
+
+mod storage;
+
+mod library_filtered;
+
+mod player;
+
+mod addon_transport;
+
+mod detail;
+
+// An add-on served from https://<host>/manifest.json; the given manifest fields override the defaults
+pub fn sample_addon(host: &str, manifest: Value) -> Descriptor {
+    let mut defaults = json!({
+        "id": host,
+        "version": "1.0.0",
+        "name": host,
+        "types": [],
+        "resources": [],
+        "catalogs": []
+    });
+    if let (Value::Object(defaults), Value::Object(manifest)) = (&mut defaults, manifest) {
+        defaults.extend(manifest);
+    }
+    serde_json::from_value(json!({
+        "transportUrl": format!("https://{}/manifest.json", host),
+        "manifest": defaults
+    }))
+    .expect("sample addon must deserialize")
+}
+
+// An add-on serving the meta and streams of series
+pub fn sample_series_addon(host: &str) -> Descriptor {
+    sample_addon(
+        host,
+        json!({ "types": ["series"], "resources": ["meta", "stream"] }),
+    )
+}
+
+// The meta of the series tt1, with that many episodes in the first season
+pub fn sample_meta(episodes: u32) -> Value {
+    let videos = (1..=episodes)
+        .map(|episode| {
+            json!({
+                "id": format!("tt1:1:{}", episode),
+                "title": "Episode",
+                "released": "2019-01-01T00:00:00.000Z",
+                "season": 1,
+                "episode": episode
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({ "id": "tt1", "type": "series", "name": "Sample", "videos": videos })
+}
+
+// A library item which is not watched yet
+pub fn sample_lib_item(id: &str, type_name: &str) -> LibItem {
+    serde_json::from_value(json!({
+        "_id": id,
+        "removed": false,
+        "temp": false,
+        "_ctime": "2019-01-01T00:00:00.000Z",
+        "_mtime": "2019-06-01T00:00:00.000Z",
+        "state": {
+            "lastWatched": "2019-01-01T00:00:00.000Z",
+            "timeWatched": 0,
+            "timeOffset": 0,
+            "overallTimeWatched": 0,
+            "timesWatched": 0,
+            "flaggedWatched": 0,
+            "duration": 0,
+            "video_id": "",
+            "watched": "",
+            "noNotif": false
+        },
+        "name": "Sample",
+        "type": type_name,
+        "poster": ""
+    }))
+    .expect("sample lib item must deserialize")
+}
@@ -0,0 +1,106 @@
+use super::*;
+use crate::state_types::*;
+use crate::types::addons::Descriptor;
+use crate::types::{LibBucket, LibItem};
+use chrono::{TimeZone, Utc};
+use serde_json::json;
+use stremio_derive::Model;
+use tokio::runtime::current_thread::run;
+
+#[derive(Model, Debug, Default)]
+struct Model {
+    ctx: Ctx<EnvMock>,
+    notifs: Notifications,
+}
+
+fn addon() -> Descriptor {
+    sample_addon(
+        "addon.example.com",
+        json!({
+            "types": ["series"],
+            "idPrefixes": ["tt"],
+            "resources": ["meta", "catalog"],
+            "catalogs": [
+                { "type": "series", "id": "top" },
+                {
+                    "type": "series",
+                    "id": "last-videos",
+                    "extra": [{ "name": "lastVideosIds", "isRequired": true }]
+                }
+            ]
+        }),
+    )
+}
+
+// A series item which last saw a video released on 2019-05-01
+fn series_lib_item(id: &str) -> LibItem {
+    let mut item = sample_lib_item(id, "series");
+    item.state.last_vid_released = Some(Utc.with_ymd_and_hms(2019, 5, 1, 0, 0, 0).unwrap());
+    item
+}
+
+#[test]
+fn only_new_videos_are_notifications() {
+    EnvMock::reset();
+    EnvMock::respond(
+        "GET",
+        "https://addon.example.com/catalog/series/last-videos/lastVideosIds=tt1.json",
+        &json!({
+            "metasDetailed": [{
+                "id": "tt1",
+                "type": "series",
+                "name": "Sample",
+                "videos": [
+                    { "id": "tt1:1:1", "title": "Old", "released": "2019-04-01T00:00:00.000Z" },
+                    { "id": "tt1:1:2", "title": "New", "released": "2019-06-01T00:00:00.000Z" }
+                ]
+            }]
+        }),
+    );
+    let mut model = Model::default();
+    model.ctx.content.addons = vec![addon()];
+    let mut muted = series_lib_item("tt2");
+    muted.state.no_notif = true;
+    model.ctx.library = LibraryLoadable::Ready(LibBucket::new(
+        Default::default(),
+        vec![
+            series_lib_item("tt1"),
+            // Those are not eligible for notifications
+            muted,
+            sample_lib_item("tt3", "movie"),
+        ],
+    ));
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(model, 1000);
+    run(runtime.dispatch(&Action::Load(ActionLoad::Notifications).into()));
+
+    assert_eq!(
+        EnvMock::request_urls(),
+        vec!["https://addon.example.com/catalog/series/last-videos/lastVideosIds=tt1.json"]
+    );
+    let model = runtime.app.read().unwrap();
+    assert_eq!(model.notifs.groups.len(), 1);
+    match &model.notifs.groups[0].content {
+        Loadable::Ready(meta_items) => {
+            assert_eq!(meta_items.len(), 1);
+            let video_ids = meta_items[0]
+                .videos
+                .iter()
+                .map(|v| v.id.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(video_ids, vec!["tt1:1:2"]);
+        }
+        x => panic!("notifs group not ready, but instead: {:?}", x),
+    }
+}
+
+#[test]
+fn no_groups_without_library() {
+    EnvMock::reset();
+    let mut model = Model::default();
+    model.ctx.content.addons = vec![addon()];
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(model, 1000);
+    run(runtime.dispatch(&Action::Load(ActionLoad::Notifications).into()));
+
+    assert!(EnvMock::requests().is_empty());
+    assert!(runtime.app.read().unwrap().notifs.groups.is_empty());
+}
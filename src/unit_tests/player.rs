@@ -0,0 +1,184 @@
+use super::*;
+use crate::state_types::*;
+use crate::types::{MetaDetail, Stream};
+use chrono::{TimeZone, Utc};
+use serde_json::json;
+use stremio_derive::Model;
+use tokio::runtime::current_thread::run;
+
+#[derive(Model, Debug, Default)]
+struct Model {
+    ctx: Ctx<EnvMock>,
+    player: Player,
+}
+
+fn stream(url: &str, binge_group: &str) -> serde_json::Value {
+    json!({ "url": url, "behaviorHints": { "bingeGroup": binge_group } })
+}
+
+// The first episode is playing, and it's in the library unless `in_library` is false
+fn play_first_episode(autoplay_next_vid: bool, in_library: bool) -> Runtime<EnvMock, Model> {
+    EnvMock::reset();
+    EnvMock::respond(
+        "GET",
+        "https://addon.example.com/meta/series/tt1.json",
+        &json!({ "meta": sample_meta(2) }),
+    );
+    EnvMock::respond(
+        "GET",
+        "https://addon.example.com/stream/series/tt1:1:2.json",
+        &json!({
+            "streams": [
+                stream("https://example.com/other/2.mp4", "other"),
+                stream("https://example.com/group/2.mp4", "group")
+            ]
+        }),
+    );
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    {
+        let mut model = runtime.app.write().unwrap();
+        model.ctx.content.addons = vec![sample_series_addon("addon.example.com")];
+        model.ctx.content.settings.autoplay_next_vid = autoplay_next_vid;
+    }
+    if in_library {
+        let meta: Box<MetaDetail> = serde_json::from_value(sample_meta(2)).unwrap();
+        run(runtime.dispatch(&Action::UserOp(ActionUser::AddToLibrary(meta)).into()));
+    }
+    let stream: Stream =
+        serde_json::from_value(stream("https://example.com/group/1.mp4", "group")).unwrap();
+    run(runtime.dispatch(
+        &Action::Load(ActionLoad::Player {
+            type_name: "series".into(),
+            id: "tt1".into(),
+            video_id: Some("tt1:1:1".into()),
+            stream: Box::new(stream),
+        })
+        .into(),
+    ));
+    EnvMock::set_now(Utc.with_ymd_and_hms(2020, 1, 2, 0, 0, 0).unwrap());
+    runtime
+}
+
+fn ended(runtime: &Runtime<EnvMock, Model>) {
+    run(runtime.dispatch(&Action::PlayerEvent(PlayerEvent::Ended).into()));
+}
+
+#[test]
+fn next_stream_is_from_the_same_binge_group() {
+    let runtime = play_first_episode(true, true);
+    let model = runtime.app.read().unwrap();
+    let next_video = model
+        .player
+        .next_video
+        .as_ref()
+        .expect("there's a next video");
+    assert_eq!(next_video.id, "tt1:1:2");
+    let next_stream = model
+        .player
+        .next_stream
+        .as_ref()
+        .expect("there's a next stream");
+    assert_eq!(
+        next_stream.behavior_hints.binge_group,
+        Some("group".to_owned())
+    );
+}
+
+#[test]
+fn ended_autoplays_the_next_video() {
+    let runtime = play_first_episode(true, true);
+    ended(&runtime);
+
+    let model = runtime.app.read().unwrap();
+    let selected = model.player.selected.as_ref().unwrap();
+    assert_eq!(selected.video_id, Some("tt1:1:2".to_owned()));
+    assert_eq!(
+        selected.stream.source,
+        serde_json::from_value(json!({ "url": "https://example.com/group/2.mp4" })).unwrap()
+    );
+    let lib_item = model.ctx.library.get("tt1").unwrap();
+    assert_eq!(lib_item.state.video_id, Some("tt1:1:2".to_owned()));
+}
+
+#[test]
+fn ended_without_autoplay_sets_the_next_video() {
+    let runtime = play_first_episode(false, true);
+    ended(&runtime);
+
+    let model = runtime.app.read().unwrap();
+    let selected = model.player.selected.as_ref().unwrap();
+    assert_eq!(
+        selected.video_id,
+        Some("tt1:1:1".to_owned()),
+        "still loaded"
+    );
+    let lib_item = model.ctx.library.get("tt1").unwrap();
+    assert_eq!(lib_item.state.video_id, Some("tt1:1:2".to_owned()));
+    assert!(lib_item.is_in_continue_watching(), "next video is up");
+}
+
+#[test]
+fn seeking_is_not_counted_as_watched() {
+    let runtime = play_first_episode(true, true);
+    let prop = |prop: PlayerProp| {
+        run(runtime.dispatch(&Action::PlayerEvent(PlayerEvent::PropChanged(prop)).into()))
+    };
+    prop(PlayerProp::Duration(3_600_000));
+    prop(PlayerProp::Time(1_000));
+    prop(PlayerProp::Time(2_000));
+    run(runtime.dispatch(&Action::PlayerOp(PlayerAction::SetProp(PlayerProp::Time(5_000))).into()));
+    run(runtime.dispatch(&Action::PlayerOp(PlayerAction::SetProp(PlayerProp::Paused(true))).into()));
+
+    let model = runtime.app.read().unwrap();
+    assert_eq!(model.player.time, Some(5_000));
+    assert_eq!(model.player.paused, Some(true));
+    let lib_item = model.player.lib_item.as_ref().unwrap();
+    assert_eq!(lib_item.state.time_offset, 5_000);
+    assert_eq!(lib_item.state.time_watched, 1_000);
+    // Pausing pushes the progress to the library
+    assert_eq!(model.ctx.library.get("tt1"), Some(lib_item));
+}
+
+#[test]
+fn progress_of_items_outside_the_library_is_tracked() {
+    let runtime = play_first_episode(true, false);
+    let prop = |prop: PlayerProp| {
+        run(runtime.dispatch(&Action::PlayerEvent(PlayerEvent::PropChanged(prop)).into()))
+    };
+    prop(PlayerProp::Duration(3_600_000));
+    prop(PlayerProp::Time(1_000));
+    prop(PlayerProp::Paused(true));
+
+    let model = runtime.app.read().unwrap();
+    let lib_item = model
+        .ctx
+        .library
+        .get("tt1")
+        .expect("tracked in the library");
+    assert!(
+        lib_item.removed && lib_item.temp,
+        "not added to the library"
+    );
+    assert_eq!(lib_item.state.time_offset, 1_000);
+    assert!(lib_item.is_in_continue_watching());
+}
+
+#[test]
+fn unload_pushes_the_progress() {
+    let runtime = play_first_episode(true, true);
+    let prop = |prop: PlayerProp| {
+        run(runtime.dispatch(&Action::PlayerEvent(PlayerEvent::PropChanged(prop)).into()))
+    };
+    prop(PlayerProp::Duration(3_600_000));
+    prop(PlayerProp::Time(1_000));
+    prop(PlayerProp::Time(2_000));
+    run(runtime.dispatch(&Action::Unload.into()));
+
+    let model = runtime.app.read().unwrap();
+    assert_eq!(model.player.selected, None);
+    assert_eq!(model.player.lib_item, None);
+    let lib_item = model.ctx.library.get("tt1").unwrap();
+    assert_eq!(lib_item.state.time_offset, 2_000);
+    assert_eq!(lib_item.state.time_watched, 1_000);
+}
@@ -0,0 +1,149 @@
+use super::*;
+use crate::state_types::*;
+use crate::types::LibBucket;
+use futures::Future;
+use serde_json::json;
+use stremio_derive::Model;
+use tokio::runtime::current_thread::run;
+
+#[derive(Model, Debug, Default)]
+struct Model {
+    ctx: Ctx<EnvMock>,
+}
+
+fn store(slot: &str, value: &serde_json::Value) {
+    EnvMock::set_storage(slot, Some(value))
+        .wait()
+        .expect("value is stored");
+}
+
+fn load_ctx() -> Runtime<EnvMock, Model> {
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(Model::default(), 1000);
+    run(runtime.dispatch(&Action::LoadCtx.into()));
+    runtime
+}
+
+#[test]
+fn fresh_storage_gets_the_latest_version() {
+    EnvMock::reset();
+    let runtime = load_ctx();
+
+    assert_eq!(
+        runtime.app.read().unwrap().ctx.content,
+        CtxContent::default()
+    );
+    assert_eq!(
+        EnvMock::get_storage_sync::<usize>("schema_version"),
+        Some(STORAGE_VERSION)
+    );
+}
+
+#[test]
+fn failed_migration_is_backed_up() {
+    EnvMock::reset();
+    let user_data = json!(["not", "user", "data"]);
+    let library = json!({ "uid": null, "items": {} });
+    let outbox = json!({ "uid": null, "items": { "tt1": "not an item" } });
+    store("userData", &user_data);
+    store("library", &library);
+    store("library_outbox", &outbox);
+    let runtime = load_ctx();
+
+    let model = runtime.app.read().unwrap();
+    assert!(model.ctx.is_loaded, "ctx is loaded");
+    assert_eq!(model.ctx.content, CtxContent::default(), "default content");
+    assert_eq!(
+        EnvMock::get_storage_sync::<serde_json::Value>("userData_backup"),
+        Some(user_data)
+    );
+    assert_eq!(
+        EnvMock::get_storage_sync::<serde_json::Value>("library_backup"),
+        Some(library)
+    );
+    assert_eq!(EnvMock::get_storage_sync::<LibBucket>("library"), None);
+    // The outbox is only loaded after the migration backed it up
+    assert!(
+        model.ctx.lib_sync.outbox.items.is_empty(),
+        "outbox is empty"
+    );
+    assert_eq!(
+        EnvMock::get_storage_sync::<serde_json::Value>("library_outbox_backup"),
+        Some(outbox)
+    );
+    assert_eq!(
+        EnvMock::get_storage_sync::<usize>("schema_version"),
+        Some(STORAGE_VERSION)
+    );
+}
+
+#[test]
+fn undeserializable_slots_are_backed_up() {
+    EnvMock::reset();
+    let user_data = json!({ "auth": null, "addons": "not addons" });
+    let recent_library = json!({ "uid": null, "items": [] });
+    store("schema_version", &json!(STORAGE_VERSION));
+    store("userData", &user_data);
+    store("recent_library", &recent_library);
+    let runtime = load_ctx();
+
+    let model = runtime.app.read().unwrap();
+    assert!(model.ctx.is_loaded, "ctx is loaded");
+    assert_eq!(model.ctx.content, CtxContent::default(), "default content");
+    assert_eq!(
+        model.ctx.library,
+        LibraryLoadable::Ready(Default::default()),
+        "library is empty"
+    );
+    assert_eq!(
+        EnvMock::get_storage_sync::<serde_json::Value>("userData_backup"),
+        Some(user_data)
+    );
+    assert_eq!(
+        EnvMock::get_storage_sync::<serde_json::Value>("recent_library_backup"),
+        Some(recent_library)
+    );
+}
+
+#[test]
+fn user_data_is_recovered_per_field() {
+    EnvMock::reset();
+    let auth = json!({
+        "key": "auth_key",
+        "user": {
+            "_id": "user_id",
+            "email": "user@stremio.com",
+            "fbId": null,
+            "avatar": null,
+            "lastModified": "2019-01-01T00:00:00.000Z",
+            "dateRegistered": "2019-01-01T00:00:00.000Z"
+        }
+    });
+    let addon = sample_addon("addon.example.com", json!({}));
+    let user_data = json!({
+        "auth": auth,
+        "addons": [addon, { "manifest": "not a manifest" }],
+        "settings": { "autoplay_next_vid": true }
+    });
+    store("schema_version", &json!(STORAGE_VERSION));
+    store("userData", &user_data);
+    let runtime = load_ctx();
+
+    let model = runtime.app.read().unwrap();
+    let content = &model.ctx.content;
+    assert_eq!(content.auth, serde_json::from_value(auth).unwrap());
+    assert_eq!(
+        content.addons,
+        vec![addon],
+        "only the broken add-on is dropped"
+    );
+    assert!(content.settings.autoplay_next_vid, "settings are kept");
+    assert_eq!(
+        EnvMock::get_storage_sync::<serde_json::Value>("userData_backup"),
+        Some(user_data)
+    );
+    assert_eq!(
+        EnvMock::get_storage_sync::<CtxContent>("userData").as_ref(),
+        Some(content),
+        "the recovered content is stored"
+    );
+}
@@ -0,0 +1,106 @@
+use super::*;
+use crate::state_types::*;
+use crate::types::addons::Descriptor;
+use crate::types::StreamQuality;
+use serde_json::json;
+use stremio_derive::Model;
+use tokio::runtime::current_thread::run;
+
+const INFO_HASH: &str = "07a9de9750158471c3302e4e95edb1107f980fa6";
+
+#[derive(Model, Debug, Default)]
+struct Model {
+    ctx: Ctx<EnvMock>,
+    streams: Streams,
+}
+
+fn addon(host: &str) -> Descriptor {
+    sample_addon(host, json!({ "types": ["movie"], "resources": ["stream"] }))
+}
+
+fn load_streams(runtime: &Runtime<EnvMock, Model>) {
+    run(runtime.dispatch(
+        &Action::Load(ActionLoad::Streams {
+            type_name: "movie".into(),
+            id: "tt1".into(),
+        })
+        .into(),
+    ));
+}
+
+#[test]
+fn streams_are_deduplicated_and_ranked() {
+    EnvMock::reset();
+    EnvMock::respond(
+        "GET",
+        "https://one.example.com/stream/movie/tt1.json",
+        &json!({
+            "streams": [
+                { "infoHash": INFO_HASH, "title": "Movie 720p" },
+                { "url": "http://example.com/movie.mp4", "title": "Movie 1080p" }
+            ]
+        }),
+    );
+    EnvMock::respond(
+        "GET",
+        "https://two.example.com/stream/movie/tt1.json",
+        &json!({
+            "streams": [
+                // The same torrent, as a magnet link
+                {
+                    "url": format!("magnet:?xt=urn:btih:{}&dn=Movie", INFO_HASH.to_uppercase()),
+                    "title": "Movie 720p"
+                },
+                { "url": "https://example.com/movie.mp4", "title": "Movie SD" }
+            ]
+        }),
+    );
+    let mut model = Model::default();
+    model.ctx.content.addons = vec![addon("one.example.com"), addon("two.example.com")];
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(model, 1000);
+    load_streams(&runtime);
+
+    let ranked = runtime.app.read().unwrap().streams.ranked.to_owned();
+    let titles = ranked
+        .iter()
+        .map(|r| r.stream.title.as_ref().unwrap().as_str())
+        .collect::<Vec<_>>();
+    // Web ready first, then HTTP before P2P, then by quality
+    assert_eq!(titles, vec!["Movie SD", "Movie 1080p", "Movie 720p"]);
+    assert_eq!(ranked[1].quality, StreamQuality::FullHD);
+    assert_eq!(
+        ranked[2].addons,
+        vec![
+            "https://one.example.com/manifest.json",
+            "https://two.example.com/manifest.json"
+        ],
+        "the torrent from both add-ons is merged"
+    );
+}
+
+#[test]
+fn ranking_follows_the_preferences() {
+    EnvMock::reset();
+    EnvMock::respond(
+        "GET",
+        "https://one.example.com/stream/movie/tt1.json",
+        &json!({
+            "streams": [
+                { "url": "https://example.com/movie.mp4", "title": "Movie 720p" },
+                { "infoHash": INFO_HASH, "title": "Movie 1080p" }
+            ]
+        }),
+    );
+    let mut model = Model::default();
+    model.ctx.content.addons = vec![addon("one.example.com")];
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(model, 1000);
+    load_streams(&runtime);
+
+    let mut settings = runtime.app.read().unwrap().ctx.content.settings.to_owned();
+    settings.stream_ranking.prefer_web_ready = false;
+    settings.stream_ranking.prefer_p2p = true;
+    run(runtime.dispatch(&Action::Settings(ActionSettings::Store(Box::new(settings))).into()));
+
+    let ranked = &runtime.app.read().unwrap().streams.ranked;
+    assert!(ranked[0].stream.is_p2p(), "P2P streams are first");
+}
@@ -0,0 +1,75 @@
+use super::*;
+use crate::state_types::*;
+use crate::types::addons::Descriptor;
+use crate::types::Stream;
+use serde_json::json;
+use stremio_derive::Model;
+use tokio::runtime::current_thread::run;
+
+#[derive(Model, Debug, Default)]
+struct Model {
+    ctx: Ctx<EnvMock>,
+    subtitles: Subtitles,
+}
+
+fn addon() -> Descriptor {
+    sample_addon(
+        "subs.example.com",
+        json!({ "types": ["movie"], "resources": ["subtitles"] }),
+    )
+}
+
+fn sub(id: &str, lang: &str) -> serde_json::Value {
+    json!({ "id": id, "lang": lang, "url": format!("https://subs.example.com/{}.srt", id) })
+}
+
+#[test]
+fn subtitles_are_grouped_by_language() {
+    EnvMock::reset();
+    EnvMock::respond(
+        "GET",
+        "https://subs.example.com/subtitles/movie/tt1/videoHash=abcd&videoSize=1024.json",
+        // Add-ons don't always agree on the language codes
+        &json!({ "subtitles": [sub("1", "bg"), sub("2", "en"), sub("embedded", "eng")] }),
+    );
+    let stream: Stream = serde_json::from_value(json!({
+        "url": "https://example.com/movie.mp4",
+        "subtitles": [sub("embedded", "eng"), sub("3", "fre")]
+    }))
+    .unwrap();
+    let mut model = Model::default();
+    model.ctx.content.addons = vec![addon()];
+    let (runtime, _) = Runtime::<EnvMock, Model>::new(model, 1000);
+    run(runtime.dispatch(
+        &Action::Load(ActionLoad::Subtitles {
+            type_name: "movie".into(),
+            id: "tt1".into(),
+            video_hash: Some("abcd".into()),
+            video_size: Some(1024),
+            stream: Box::new(stream),
+        })
+        .into(),
+    ));
+
+    let languages = &runtime.app.read().unwrap().subtitles.languages;
+    let summary = languages
+        .iter()
+        .map(|l| {
+            let ids = l
+                .subtitles
+                .iter()
+                .map(|s| s.id.as_str())
+                .collect::<Vec<_>>();
+            (l.lang.code(), ids)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![
+            ("eng", vec!["embedded", "2"]),
+            ("fre", vec!["3"]),
+            ("bul", vec!["1"]),
+        ],
+        "preferred language first, without duplicates"
+    );
+}